@@ -1,30 +1,92 @@
 use chrono::Local;
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind},
     widgets::ListState,
 };
-use std::{collections::HashSet, fs::write, path::Path};
+use signal_hook::consts::SIGTERM;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{write, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use tui_input::{backend::crossterm::EventHandler, Input};
 
 use crate::{
+    command::{self, Command, Substitute},
     config::Config,
-    tasks::{Task, DATE_FORMAT_CONST},
+    editor::{self, note_file_name, EditorRequest},
+    keymap::{Action, KeyChord, Keymap, Lookup},
+    links::{
+        blocked_tasks, dependency_cycles, dependents, descendants, next_id, subtask_progress,
+        task_tree,
+    },
+    mouse::{ClickTarget, MouseAreas},
+    notices::Notices,
+    palette::{Palette, PaletteEntry},
+    readline::{self, History},
+    tasks::{
+        complete_project, is_subproject, project_parents, project_segments, with_creation_date,
+        SortKey, Task, DATE_FORMAT_CONST,
+    },
+    vim::{Mode, Vim},
 };
 
 const PENDING_PREFIX: &str = "☐ ";
 const PROJECT_PREFIX: &str = "+";
 const CONTEXT_PREFIX: &str = "@";
+const UNDO_LIMIT: usize = 100;
+/// the longest time between the clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+/// the longest wait for an event, how soon a signal to quit is noticed
+const TICK: Duration = Duration::from_millis(250);
 
 pub fn run_app(terminal: &mut crate::tui::Tui, model: &mut Model) -> color_eyre::Result<bool> {
     model.list_state.select(Some(0));
+    let terminated = register_signals()?;
     while model.live_state != LiveState::Done {
+        // the tasks are saved once the loop is left, before the terminal is drawn to again
+        if terminated.load(Ordering::Relaxed) {
+            break;
+        }
         terminal.draw(|f| crate::ui::view(model, f))?;
 
-        let mut current_msg = handle_events(model)?;
+        let event = handle_events(model);
+        if terminated.load(Ordering::Relaxed) {
+            break;
+        }
+        let mut current_msg = event?;
+        model.notices.expire(Instant::now());
+        if model.autosave_in(Instant::now()) == Some(Duration::ZERO) {
+            model.autosave();
+        }
 
         while current_msg.is_some() {
             current_msg = update(model, current_msg.unwrap());
         }
+
+        if let Some(request) = model.editor_request.take() {
+            crate::tui::restore()?;
+            let opened = match editor::open(request.path()) {
+                Ok(()) => true,
+                Err(error) => {
+                    model
+                        .notices
+                        .error(format!("Failed to open the editor: {error}"));
+                    false
+                }
+            };
+            *terminal = crate::tui::init(model.config.mouse)?;
+            let mut current_msg = Some(Message::EditorClosed(request, opened));
+            while current_msg.is_some() {
+                current_msg = update(model, current_msg.unwrap());
+            }
+        }
     }
 
     Ok(model.save_file)
@@ -45,9 +107,57 @@ pub struct Model {
     pub auto_complete: Option<Autocomplete>,
     pub config: Config,
     pub save_file: bool,
+    /// whether the tasks changed since they were read or saved last
+    pub dirty: bool,
+    /// when the tasks changed last, the autosave waits for the changes to settle
+    pub changed_at: Instant,
     pub search: SearchInput,
     pub saved_searches: SavedSearches,
     pub report: String,
+    pub marked: HashSet<u64>,
+    pub mark_anchor: Option<usize>,
+    pub undo_stack: UndoStack,
+    /// archived tasks waiting to be appended to the archive file, the ones brought back by an
+    /// undo are kept until the next change so that a redo archives them again
+    pub archived: Vec<Task>,
+    /// uids of the archived tasks already appended, the undo doesn't bring them back
+    pub archive_saved: HashSet<u64>,
+    pub sidebar: Sidebar,
+    pub focus: Focus,
+    /// whether the detail pane of the selected task is shown
+    pub detail: bool,
+    /// a file to open in the external editor once the current messages are handled
+    pub editor_request: Option<EditorRequest>,
+    pub task_history: History,
+    pub search_history: History,
+    /// the text killed last in an input, yanked back with Ctrl-y
+    pub kill_buffer: String,
+    pub vim: Vim,
+    /// how deep each task in `filtered_tasks` is nested under its parents
+    pub list_depths: Vec<usize>,
+    /// done and total subtasks by the id of the parent
+    pub subtask_progress: HashMap<String, (usize, usize)>,
+    pub has_subtasks: bool,
+    /// ids of the parents whose subtasks are hidden
+    pub collapsed: HashSet<String>,
+    /// uids of the open tasks waiting for an unfinished dependency
+    pub blocked: HashSet<u64>,
+    pub dependency_cycles: Vec<Vec<String>>,
+    pub keymap: Keymap,
+    /// the messages shown in the status line until they expire
+    pub notices: Notices,
+    pub pending: Pending,
+    /// uids of the tasks with a vim style mark by its letter
+    pub jump_marks: HashMap<char, u64>,
+    /// rows of the task list, the size of a page
+    pub list_height: usize,
+    pub mouse_areas: MouseAreas,
+    /// the inputs the command line can be completed to
+    pub command_completions: Vec<String>,
+    /// the note of the selected task shown in the detail pane
+    pub note_preview: Option<NotePreview>,
+    /// when and which task of the list was clicked last, to find double clicks
+    pub last_click: Option<(Instant, usize)>,
 }
 
 impl Model {
@@ -65,42 +175,13 @@ impl Model {
                 }
             })
         });
-        let (tasks, first_done_index) = {
-            let tasks: Vec<Task> = tasks
-                .iter()
-                .filter(|e| {
-                    let temp = e.trim();
-
-                    !(temp.is_empty() || temp == "x")
-                })
-                .map(|a| Task::new(a))
-                .collect();
-            if config.move_done_to_end {
-                let mut todo_task: Vec<Task> = Vec::with_capacity(tasks.len());
-                let mut incomplete_tasks = Vec::new();
-
-                for task in tasks {
-                    if !task.done {
-                        todo_task.push(task)
-                    } else {
-                        incomplete_tasks.push(task)
-                    }
-                }
-                let first_done_index = todo_task.len();
-
-                todo_task.append(&mut incomplete_tasks);
-
-                (todo_task, first_done_index)
-            } else {
-                (tasks, usize::MAX)
-            }
-        };
+        let (tasks, first_done_index) = parse_tasks(&tasks, config.move_done_to_end);
 
         let nums: Vec<String> = (0..tasks.len())
             .map(|e| e.to_string())
             .collect::<Vec<String>>();
 
-        Self {
+        let mut model = Self {
             live_state: LiveState::Running,
             app_state: AppState::List,
             list_state: ListState::default(),
@@ -113,34 +194,117 @@ impl Model {
             projects,
             context,
             auto_complete: None,
-            config,
             save_file: true,
+            dirty: false,
+            changed_at: Instant::now(),
             saved_searches: SavedSearches::new(saved_searches),
             report: String::from(""),
+            marked: HashSet::new(),
+            mark_anchor: None,
+            undo_stack: UndoStack::default(),
+            archived: Vec::new(),
+            archive_saved: HashSet::new(),
+            sidebar: Sidebar {
+                visible: config.sidebar,
+                list_state: ListState::default().with_selected(Some(0)),
+            },
+            focus: Focus::List,
+            detail: config.detail,
+            editor_request: None,
+            task_history: History::load(&config.task_history_path),
+            search_history: History::load(&config.search_history_path),
+            kill_buffer: String::new(),
+            vim: Vim::default(),
+            keymap: Keymap::new(&config.keys).unwrap_or_default(),
+            notices: Notices::default(),
+            pending: Pending::default(),
+            jump_marks: HashMap::new(),
+            list_height: 0,
+            mouse_areas: MouseAreas::default(),
+            last_click: None,
+            note_preview: None,
+            command_completions: Vec::new(),
+            config,
+            list_depths: Vec::new(),
+            subtask_progress: HashMap::new(),
+            has_subtasks: false,
+            collapsed: HashSet::new(),
+            blocked: HashSet::new(),
+            dependency_cycles: Vec::new(),
+        };
+        model.filter_tasks();
+        let unreadable = model
+            .tasks
+            .iter()
+            .filter(|t| !t.done && t.recurrence().is_some() && t.next_due().is_none())
+            .count();
+        if unreadable > 0 {
+            model.notices.warn(format!(
+                "The recurrence of {} can't be computed, check their rec: and due:",
+                count_tasks(unreadable)
+            ));
         }
+        model
     }
 
-    pub fn write(&self) -> std::io::Result<()> {
-        if self.save_file {
+    /// saves the tasks and the archived tasks, the file is left untouched when nothing changed
+    pub fn write(&mut self) -> std::io::Result<()> {
+        if self.save_file && self.dirty {
+            // the archive is written first, so the tasks stay in the todo file when it fails
+            let (restored, archived): (Vec<Task>, Vec<Task>) = std::mem::take(&mut self.archived)
+                .into_iter()
+                .partition(|t| self.task_position(t.uid).is_some());
+            self.archived = restored;
+            if !archived.is_empty() {
+                let lines = archived.iter().map(task_line).collect::<Vec<String>>();
+                let appended = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.config.archive_path.as_str())
+                    .and_then(|mut file| writeln!(file, "{}", lines.join("\n")));
+                if let Err(e) = appended {
+                    self.archived.extend(archived);
+                    return Err(e);
+                }
+                self.archive_saved.extend(archived.iter().map(|t| t.uid));
+            }
             let content = self
                 .tasks
                 .iter()
-                .map(|a| {
-                    if a.done {
-                        a.text.clone()
-                    } else {
-                        a.text.strip_prefix(PENDING_PREFIX).unwrap().to_string()
-                    }
-                })
+                .map(task_line)
                 .collect::<Vec<String>>()
                 .join("\n");
             let path = Path::new(self.config.file_path.as_str());
-            write(path, content)
+            write(path, content)?;
+            self.dirty = false;
+            Ok(())
         } else {
             Ok(())
         }
     }
 
+    fn changed(&mut self) {
+        self.dirty = true;
+        self.changed_at = Instant::now();
+    }
+
+    /// how long until the changes are autosaved, None when there is nothing to save or it's off
+    pub fn autosave_in(&self, now: Instant) -> Option<Duration> {
+        if !self.dirty || self.config.autosave == 0 {
+            return None;
+        }
+        let due = self.changed_at + Duration::from_secs(self.config.autosave);
+        Some(due.saturating_duration_since(now))
+    }
+
+    /// saves the changes, a failed save is tried again after another delay
+    fn autosave(&mut self) {
+        if let Err(error) = self.write() {
+            self.changed_at = Instant::now();
+            self.notices.error(format!("Failed to autosave: {error}"));
+        }
+    }
+
     /// get context and projets from string and add to the sets
     pub fn add_to_sets(&mut self, task: &str) {
         task.split_whitespace().for_each(|t| {
@@ -158,6 +322,14 @@ impl Model {
         });
     }
 
+    /// recollects the projects and contexts from the tasks, dropping the ones no task uses anymore
+    fn rebuild_sets(&mut self) {
+        self.projects.clear();
+        self.context.clear();
+        let texts: Vec<String> = self.tasks.iter().map(|t| t.text.clone()).collect();
+        texts.iter().for_each(|text| self.add_to_sets(text));
+    }
+
     pub fn new_task(&mut self, value: String) {
         if !value.trim().is_empty() {
             let task = Task::new(&value);
@@ -168,72 +340,901 @@ impl Model {
             self.nums.push(new_num);
         }
 
-        if !self.search.is_empty() {
-            self.filter_tasks()
+        self.filter_tasks()
+    }
+
+    /// the tasks shown in the list, which are the filtered tasks while a search is active
+    /// or the tasks ordered as a tree when there are subtasks
+    pub fn visible_tasks(&self) -> &[Task] {
+        if self.search.is_empty() && !self.has_subtasks {
+            &self.tasks
+        } else {
+            &self.filtered_tasks
+        }
+    }
+
+    fn task_position(&self, uid: u64) -> Option<usize> {
+        self.tasks.iter().position(|t| t.uid == uid)
+    }
+
+    fn selected_uid(&self) -> Option<u64> {
+        let index = self.list_state.selected()?;
+        self.visible_tasks().get(index).map(|t| t.uid)
+    }
+
+    /// where the note of the task is stored
+    pub fn note_path(&self, task: &Task) -> Option<PathBuf> {
+        task.note()
+            .map(|file| Path::new(&self.config.notes_dir).join(file))
+    }
+
+    /// reads the note of the selected task for the detail pane unless it's read already
+    pub fn load_note_preview(&mut self) {
+        let path = self.selected_task().and_then(|task| self.note_path(task));
+        if self.note_preview.as_ref().map(|p| &p.path) == path.as_ref() {
+            return;
         }
+        self.note_preview = path.map(|path| NotePreview {
+            text: std::fs::read_to_string(&path).ok(),
+            path,
+        });
     }
 
-    fn update_task(&mut self, only_toggle: bool) {
-        let value = self.input.value().to_string();
-        let index = if let Some(index) = self.list_state.selected() {
-            if self.search.is_empty() {
-                index
-            } else {
-                let text = &self.filtered_tasks[index].text;
-                self.tasks
-                    .iter()
-                    .position(|t| t.text == *text)
-                    .unwrap_or_default()
+    /// opens the note of the selected task in the editor, a task without a note gets a new file
+    fn open_note(&mut self) {
+        let Some(task) = self.selected_task() else {
+            return;
+        };
+        let dir = Path::new(&self.config.notes_dir);
+        let file = match task.note() {
+            Some(file) => file.to_string(),
+            None => note_file_name(&task.description_words(), dir),
+        };
+        self.editor_request = Some(EditorRequest::Note {
+            uid: task.uid,
+            path: dir.join(&file),
+            file,
+        });
+    }
+
+    /// the task selected in the list
+    pub fn selected_task(&self) -> Option<&Task> {
+        self.visible_tasks().get(self.list_state.selected()?)
+    }
+
+    /// index in `tasks` of the task selected in the list, which may be showing the filtered tasks
+    fn selected_task_index(&self) -> Option<usize> {
+        self.task_position(self.selected_uid()?)
+    }
+
+    /// uids of the tasks an action applies to, the marked tasks if there are any or else the selected one
+    fn target_uids(&mut self) -> Vec<u64> {
+        if let Some(uids) = self.pending.targets.take() {
+            return uids;
+        }
+        self.mark_range();
+        if self.marked.is_empty() {
+            self.selected_uid().into_iter().collect()
+        } else {
+            self.tasks
+                .iter()
+                .filter(|t| self.marked.contains(&t.uid))
+                .map(|t| t.uid)
+                .collect()
+        }
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(uid) = self.selected_uid() {
+            if !self.marked.remove(&uid) {
+                self.marked.insert(uid);
             }
+            let len = self.visible_tasks().len();
+            list_next_cyclic(&mut self.list_state, len);
+        }
+    }
+
+    /// starts a range of marked tasks at the selection, or marks the range if one was started
+    fn toggle_mark_range(&mut self) {
+        if self.mark_anchor.is_some() {
+            self.mark_range()
         } else {
-            0
+            self.mark_anchor = self.list_state.selected();
+        }
+    }
+
+    fn mark_range(&mut self) {
+        if let (Some(anchor), Some(index)) = (self.mark_anchor.take(), self.list_state.selected()) {
+            let range = anchor.min(index)..=anchor.max(index);
+            let uids: Vec<u64> = self
+                .visible_tasks()
+                .get(range)
+                .unwrap_or_default()
+                .iter()
+                .map(|t| t.uid)
+                .collect();
+            self.marked.extend(uids);
+        }
+    }
+
+    /// marks every visible task, or unmarks them if they are all marked already
+    fn toggle_mark_all(&mut self) {
+        self.mark_anchor = None;
+        let uids: Vec<u64> = self.visible_tasks().iter().map(|t| t.uid).collect();
+        if uids.iter().all(|uid| self.marked.contains(uid)) {
+            uids.iter().for_each(|uid| {
+                self.marked.remove(uid);
+            })
+        } else {
+            self.marked.extend(uids)
+        }
+    }
+
+    /// whether the task at the index of the list is marked or is inside the range being marked
+    pub fn is_marked(&self, index: usize) -> bool {
+        let in_range = match (self.mark_anchor, self.list_state.selected()) {
+            (Some(anchor), Some(selected)) => {
+                (anchor.min(selected)..=anchor.max(selected)).contains(&index)
+            }
+            _ => false,
         };
-        if only_toggle {
-            let task = self.tasks[index].toggle_done();
+        in_range
+            || self
+                .visible_tasks()
+                .get(index)
+                .is_some_and(|t| self.marked.contains(&t.uid))
+    }
+
+    pub fn marked_count(&self) -> usize {
+        self.marked.len()
+    }
+
+    fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.mark_anchor = None;
+    }
+
+    fn update_task(&mut self) {
+        let value = self.input.value().to_string();
+        let Some(uid) = self.selected_uid() else {
+            return;
+        };
+        self.checkpoint("edit task");
+        self.replace_task(uid, &value);
+        self.refresh();
+    }
+
+    /// replaces the text of the task while keeping its uid
+    fn replace_task(&mut self, uid: u64, value: &str) {
+        let Some(index) = self.task_position(uid) else {
+            return;
+        };
+        let mut new_task = Task::new(value);
+        new_task.uid = uid;
+        let move_task = self.tasks[index].done != new_task.done;
+        self.tasks[index] = new_task;
+        if move_task {
             self.move_done_tasks(index);
-            if let Some(new_task_string) = task {
-                self.new_task(new_task_string)
+        }
+    }
+
+    /// opens the selected task, or every task when `whole_file` is set, in the editor
+    fn open_in_editor(&mut self, whole_file: bool) {
+        let (kind, uid, original) = if whole_file {
+            let lines: Vec<String> = self.tasks.iter().map(task_line).collect();
+            ("todo", None, lines.join("\n"))
+        } else {
+            let Some(task) = self.selected_task() else {
+                return;
+            };
+            ("task", Some(task.uid), task_line(task))
+        };
+        let path = match editor::create_temp_file(kind, &format!("{original}\n")) {
+            Ok(path) => path,
+            Err(error) => {
+                self.notices
+                    .error(format!("Failed to create a temporary file: {error}"));
+                return;
             }
+        };
+        self.editor_request = Some(match uid {
+            Some(uid) => EditorRequest::Task {
+                uid,
+                path,
+                original,
+            },
+            None => EditorRequest::File { path, original },
+        });
+    }
+
+    /// The edited task replaces the task, more lines are added as new tasks
+    /// and removing every line deletes it
+    fn apply_task_edit(&mut self, uid: u64, text: &str) {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+        let Some((first, rest)) = lines.split_first() else {
+            self.delete_tasks(vec![uid]);
+            return;
+        };
+        self.checkpoint("edit task");
+        self.replace_task(uid, first);
+        for line in rest {
+            self.new_task(line.to_string());
+        }
+        self.refresh();
+        self.select_uid(uid);
+    }
+
+    /// replaces every task with the lines of the edited file,
+    /// an unchanged line keeps its task so marks and the selection stay on it
+    fn apply_file_edit(&mut self, text: &str) {
+        let selected = self.selected_uid();
+        self.checkpoint("edit file");
+        let mut old_tasks: Vec<Option<Task>> = self.tasks.drain(..).map(Some).collect();
+        self.tasks = text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|line| {
+                let new_task = Task::new(line);
+                let line = task_line(&new_task);
+                old_tasks
+                    .iter_mut()
+                    .find(|t| t.as_ref().is_some_and(|t| task_line(t) == line))
+                    .and_then(Option::take)
+                    .unwrap_or(new_task)
+            })
+            .collect();
+        if self.config.move_done_to_end {
+            self.tasks.sort_by_key(|t| t.done);
+        }
+        self.marked
+            .retain(|uid| self.tasks.iter().any(|t| t.uid == *uid));
+        self.refresh();
+        if let Some(uid) = selected {
+            self.select_uid(uid);
+        }
+    }
+
+    /// adds a task for every line with the words of `suffix` added to each of them
+    fn add_tasks(&mut self, lines: &[String], suffix: &str) {
+        let lines: Vec<&str> = lines
+            .iter()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .collect();
+        if lines.is_empty() {
+            return;
+        }
+        self.checkpoint(format!("add {}", count_tasks(lines.len())));
+        let date = Local::now()
+            .format_with_items(DATE_FORMAT_CONST)
+            .to_string();
+        for line in lines {
+            let line = if self.config.add_creation_date {
+                with_creation_date(line, &date)
+            } else {
+                line.to_string()
+            };
+            let mut task = Task::new(&line);
+            for word in suffix.split_whitespace() {
+                task.add_word(word);
+            }
+            self.new_task(task_line(&task));
+        }
+        self.refresh();
+    }
+
+    /// completions are offered while typing in insert mode, not while moving in normal mode
+    fn vim_autocomplete(&mut self) -> Option<Message> {
+        if self.vim.mode == Mode::Insert {
+            Some(Message::HandleAutoComplete)
         } else {
-            let new_task = Task::new(&value);
-            let move_task = self.tasks[index].done != new_task.done;
-            self.add_to_sets(&new_task.text);
-            self.tasks[index] = new_task;
-            if move_task {
+            self.auto_complete = None;
+            None
+        }
+    }
+
+    /// selects the task in the list if it's shown
+    fn select_uid(&mut self, uid: u64) {
+        if let Some(index) = self.visible_tasks().iter().position(|t| t.uid == uid) {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    fn toggle_done(&mut self, uids: Vec<u64>) {
+        if uids.is_empty() {
+            return;
+        }
+        self.checkpoint(format!("toggle done on {}", count_tasks(uids.len())));
+        for uid in uids {
+            if let Some(index) = self.task_position(uid) {
+                let task = self.tasks[index].toggle_done();
+                if task.is_none()
+                    && self.tasks[index].done
+                    && self.tasks[index].recurrence().is_some()
+                {
+                    self.notices.warn(format!(
+                        "No next task, the recurrence of '{}' can't be computed",
+                        self.tasks[index].description_words().join(" ")
+                    ));
+                }
                 self.move_done_tasks(index);
+                if let Some(new_task_string) = task {
+                    self.new_task(new_task_string)
+                }
+            }
+        }
+        self.clear_marks();
+        self.refresh();
+    }
+
+    fn modify_tasks(&mut self, description: &str, uids: Vec<u64>, change: impl Fn(&mut Task)) {
+        if uids.is_empty() {
+            return;
+        }
+        self.checkpoint(format!("{description} on {}", count_tasks(uids.len())));
+        for uid in uids {
+            if let Some(index) = self.task_position(uid) {
+                change(&mut self.tasks[index]);
+            }
+        }
+        self.clear_marks();
+        self.refresh();
+    }
+
+    /// changes an option of the config for this session, the names are in `command::SETTINGS`
+    fn set_option(&mut self, option: &str, value: bool) {
+        match option {
+            "add_creation_date" => self.config.add_creation_date = value,
+            "detail" => self.detail = value,
+            "prompt_complete_subtasks" => self.config.prompt_complete_subtasks = value,
+            "vim_mode" => self.config.vim_mode = value,
+            "sidebar" => {
+                self.sidebar.visible = value;
+                self.focus = Focus::List;
+            }
+            "move_done_to_end" => {
+                if value && !self.config.move_done_to_end {
+                    self.checkpoint("move the done tasks to the end");
+                    self.tasks.sort_by_key(|t| t.done);
+                } else if !value {
+                    self.first_done_index = usize::MAX;
+                }
+                self.config.move_done_to_end = value;
+                self.refresh();
+            }
+            _ => {}
+        }
+    }
+
+    /// the path of a file given to `:open`, relative paths start in the directory of the todo file
+    fn resolve_path(&self, file: &str) -> PathBuf {
+        let dir = Path::new(&self.config.file_path)
+            .parent()
+            .unwrap_or(Path::new(""));
+        dir.join(file)
+    }
+
+    /// saves the tasks and switches to the tasks of another todo file
+    fn open_file(&mut self, file: &str) -> Result<(), String> {
+        let path = self.resolve_path(file);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+        self.write()
+            .map_err(|e| format!("Failed to save {}: {e}", self.config.file_path))?;
+        let lines: Vec<&str> = text.lines().collect();
+        let (tasks, first_done_index) = parse_tasks(&lines, self.config.move_done_to_end);
+        self.tasks = tasks;
+        self.first_done_index = first_done_index;
+        self.config.file_path = path.to_string_lossy().to_string();
+        self.undo_stack = UndoStack::default();
+        self.archived.clear();
+        self.archive_saved.clear();
+        self.dirty = false;
+        self.collapsed.clear();
+        self.jump_marks.clear();
+        self.note_preview = None;
+        self.clear_marks();
+        self.refresh();
+        self.list_state.select(Some(0));
+        Ok(())
+    }
+
+    /// the candidates for the argument of a command, files for `:open` and tags for `:filter`
+    fn command_arguments(&self, command: &str, argument: &str) -> Vec<String> {
+        match command {
+            "filter" => {
+                let mut tags: Vec<String> = self
+                    .projects
+                    .iter()
+                    .map(|p| format!("{PROJECT_PREFIX}{p}"))
+                    .chain(self.context.iter().map(|c| format!("{CONTEXT_PREFIX}{c}")))
+                    .collect();
+                tags.sort();
+                tags
+            }
+            "open" => {
+                // the directory typed so far is kept in front of the file names
+                let typed_dir = match argument.rfind('/') {
+                    Some(i) => &argument[..=i],
+                    None => "",
+                };
+                let Ok(entries) = std::fs::read_dir(self.resolve_path(typed_dir)) else {
+                    return Vec::new();
+                };
+                let mut files: Vec<String> = entries
+                    .flatten()
+                    .map(|entry| {
+                        let name = entry.file_name().to_string_lossy().to_string();
+                        if entry.path().is_dir() {
+                            format!("{typed_dir}{name}/")
+                        } else {
+                            format!("{typed_dir}{name}")
+                        }
+                    })
+                    .collect();
+                files.sort();
+                files
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// finds the inputs the command line can be completed to, after its input changed
+    fn update_command_completions(&mut self) {
+        self.command_completions = match self.app_state {
+            AppState::Goto(ref input) => command::complete(input, |command, argument| {
+                self.command_arguments(command, argument)
+            }),
+            _ => Vec::new(),
+        };
+    }
+
+    /// runs a command of the command line, the errors are shown in the status line
+    fn run_command(&mut self, command: Command) -> Option<Message> {
+        match command {
+            Command::Goto(num) => {
+                if num < self.visible_tasks().len() {
+                    self.list_state.select(Some(num));
+                } else {
+                    self.notices.error(format!("There is no task {num}"));
+                }
+                None
+            }
+            Command::Write => Some(Message::SaveFile),
+            Command::Quit => Some(Message::Quit),
+            // the ! of `:q!` already says the changes are to be dropped
+            Command::QuitWithoutSave => Some(Message::Discard),
+            Command::Sort(key) => Some(Message::Sort(key)),
+            Command::Archive => Some(Message::Archive),
+            Command::Filter(search) => Some(Message::LoadSearch(search)),
+            Command::Set(option, value) => {
+                self.set_option(&option, value);
+                None
+            }
+            Command::Open(file) => {
+                if let Err(error) = self.open_file(&file) {
+                    self.notices.error(error);
+                }
+                None
             }
+            Command::Move(num) => Some(Message::MoveTaskTo(num)),
+            Command::Substitute(substitute) => Some(Message::PreviewSubstitute(substitute)),
+        }
+    }
+
+    /// Moves the task to the position of its sibling at or before the index of the list, the
+    /// order of the file changes so it's kept when saved. Subtasks stay under their parent and
+    /// follow it, and with `move_done_to_end` a task stays among the tasks with the same done state
+    fn move_task(&mut self, uid: u64, to: usize) {
+        let Some(from) = self.visible_tasks().iter().position(|t| t.uid == uid) else {
+            return;
+        };
+        let siblings = self.sibling_rows(from);
+        let Some(to) = siblings
+            .iter()
+            .rev()
+            .find(|row| **row <= to)
+            .or(siblings.first())
+            .copied()
+        else {
+            return;
+        };
+        if to == from {
+            return;
+        }
+        let target = self.visible_tasks()[to].uid;
+        let (Some(index), Some(target_index)) =
+            (self.task_position(uid), self.task_position(target))
+        else {
+            return;
+        };
+        self.checkpoint("move task");
+        let task = self.tasks.remove(index);
+        // the target moved up a line when the task was before it
+        let target_index = if index < target_index {
+            target_index - 1
+        } else {
+            target_index
+        };
+        let insert_at = if to > from {
+            target_index + 1
+        } else {
+            target_index
+        };
+        self.tasks.insert(insert_at, task);
+        self.refresh();
+        self.select_uid(uid);
+    }
+
+    /// the rows of the list a task can move to, the tasks with the same parent as the row.
+    /// With `move_done_to_end` only the ones with the same done state
+    fn sibling_rows(&self, row: usize) -> Vec<usize> {
+        let list = self.visible_tasks();
+        let Some(task) = list.get(row) else {
+            return Vec::new();
+        };
+        let depth_of = |r: usize| self.list_depths.get(r).copied().unwrap_or(0);
+        let depth = depth_of(row);
+        // the subtasks of a parent follow it until a row which isn't nested under it
+        let start = (0..row)
+            .rev()
+            .find(|r| depth_of(*r) < depth)
+            .map_or(0, |parent| parent + 1);
+        (start..list.len())
+            .take_while(|r| depth_of(*r) >= depth)
+            .filter(|r| depth_of(*r) == depth)
+            .filter(|r| !self.config.move_done_to_end || list[*r].done == task.done)
+            .collect()
+    }
+
+    /// a count limited to the length of the list, a larger one moves no further
+    fn visible_steps(&self, count: usize) -> isize {
+        count.min(self.visible_tasks().len()) as isize
+    }
+
+    /// the rows a page down moves
+    fn page_size(&self) -> usize {
+        self.list_height.max(2)
+    }
+
+    /// the index of the next or previous open task in the list whose due date has passed,
+    /// the search wraps around the list
+    fn overdue_index(&self, forward: bool) -> Option<usize> {
+        let today = Local::now().date_naive();
+        let list = self.visible_tasks();
+        let len = list.len();
+        let selected = self.list_state.selected().unwrap_or(0);
+        (1..=len)
+            .map(|step| {
+                if forward {
+                    (selected + step) % len
+                } else {
+                    (selected + len - step) % len
+                }
+            })
+            .find(|i| !list[*i].done && list[*i].due().is_some_and(|due| due < today))
+    }
+
+    /// sorts every task, the selected task stays selected
+    fn sort_tasks(&mut self, key: SortKey) {
+        let selected = self.selected_uid();
+        self.checkpoint(format!("sort by {}", key.name()));
+        let move_done_to_end = self.config.move_done_to_end;
+        self.tasks.sort_by(|a, b| {
+            let done = if move_done_to_end {
+                a.done.cmp(&b.done)
+            } else {
+                std::cmp::Ordering::Equal
+            };
+            done.then(key.compare(a, b))
+        });
+        self.refresh();
+        if let Some(uid) = selected {
+            self.select_uid(uid);
+        }
+    }
+
+    fn delete_tasks(&mut self, uids: Vec<u64>) {
+        if uids.is_empty() {
+            return;
+        }
+        self.checkpoint(format!("delete {}", count_tasks(uids.len())));
+        self.tasks.retain(|t| !uids.contains(&t.uid));
+        self.clear_marks();
+        self.refresh();
+    }
+
+    /// moves the tasks out of the todo file, they are appended to the archive file on the next save
+    fn archive_tasks(&mut self, uids: Vec<u64>) {
+        if uids.is_empty() {
+            return;
+        }
+        self.checkpoint(format!("archive {}", count_tasks(uids.len())));
+        let (archived, tasks) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|t| uids.contains(&t.uid));
+        self.tasks = tasks;
+        self.archived.extend(archived);
+        self.clear_marks();
+        self.refresh();
+    }
+
+    /// uids of the marked tasks, or of every done task when nothing is marked or counted
+    fn archive_targets(&mut self) -> Vec<u64> {
+        if self.pending.targets.is_none() && self.marked.is_empty() && self.mark_anchor.is_none() {
+            self.tasks
+                .iter()
+                .filter(|t| t.done)
+                .map(|t| t.uid)
+                .collect()
+        } else {
+            self.target_uids()
+        }
+    }
+
+    fn substitute_changes(&self, substitute: &Substitute) -> Vec<SubstituteChange> {
+        let list = if substitute.whole_file {
+            &self.tasks
+        } else {
+            self.visible_tasks()
         };
+        list.iter()
+            .filter_map(|t| {
+                let old = task_line(t);
+                substitute.apply(&old).map(|new| SubstituteChange {
+                    uid: t.uid,
+                    old,
+                    new,
+                })
+            })
+            .collect()
+    }
 
-        if !self.search.is_empty() {
-            self.filter_tasks()
+    /// replaces the tasks with the substituted lines, a line left empty deletes the task
+    fn apply_substitute(&mut self, changes: &[SubstituteChange]) {
+        self.checkpoint(format!("substitute on {}", count_tasks(changes.len())));
+        // emptied tasks are removed at the end, removing them here would shift the done tasks
+        let mut emptied = Vec::new();
+        for change in changes {
+            if let Some(index) = self.task_position(change.uid) {
+                if change.new.trim().is_empty() {
+                    emptied.push(change.uid);
+                    continue;
+                }
+                let mut task = Task::new(&change.new);
+                task.uid = change.uid;
+                let move_task = self.tasks[index].done != task.done;
+                self.tasks[index] = task;
+                if move_task {
+                    self.move_done_tasks(index);
+                }
+            }
+        }
+        self.tasks.retain(|t| !emptied.contains(&t.uid));
+        self.refresh();
+    }
+
+    /// Every project and context with the number of open and done tasks using it, projects come first.
+    /// Nested projects are listed under their parents, which count the tasks of the nested ones too
+    pub fn tag_counts(&self) -> Vec<TagEntry> {
+        let mut projects: Vec<&str> = self
+            .projects
+            .iter()
+            .flat_map(|p| project_parents(p).chain([p.as_str()]))
+            .collect::<HashSet<&str>>()
+            .into_iter()
+            .collect();
+        let mut contexts: Vec<&String> = self.context.iter().collect();
+        projects.sort_by_key(|p| project_segments(p));
+        contexts.sort();
+        let count = |tag: String, depth: usize, has_tag: &dyn Fn(&Task) -> bool| {
+            let (done, open) =
+                self.tasks
+                    .iter()
+                    .filter(|t| has_tag(t))
+                    .fold((0, 0), |(done, open), t| {
+                        if t.done {
+                            (done + 1, open)
+                        } else {
+                            (done, open + 1)
+                        }
+                    });
+            TagEntry {
+                tag,
+                depth,
+                open,
+                done,
+            }
+        };
+        let projects = projects.into_iter().map(|p| {
+            let depth = project_parents(p).count();
+            count(PROJECT_PREFIX.to_string() + p, depth, &|t: &Task| {
+                t.projects().any(|w| is_subproject(w, p))
+            })
+        });
+        let contexts = contexts.into_iter().map(|c| {
+            count(CONTEXT_PREFIX.to_string() + c, 0, &|t: &Task| {
+                t.contexts().any(|w| w == c)
+            })
+        });
+        projects.chain(contexts).collect()
+    }
+
+    /// whether the project is used by a task or is the parent of one which is
+    fn is_known_project(&self, project: &str) -> bool {
+        self.projects.iter().any(|p| is_subproject(p, project))
+    }
+
+    /// adds the term to the search, or removes it if the search already has it
+    fn toggle_search_term(&mut self, term: &str) {
+        let value = self.search.input.value();
+        let mut terms: Vec<&str> = value
+            .split(',')
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .collect();
+        match terms.iter().position(|t| *t == term) {
+            Some(index) => {
+                terms.remove(index);
+            }
+            None => terms.push(term),
+        };
+        self.search.input = Input::new(terms.join(","));
+        self.filter_tasks();
+        self.list_state.select(Some(0));
+    }
+
+    /// whether the search has the term, which is how the sidebar shows the tags it filters on
+    pub fn has_search_term(&self, term: &str) -> bool {
+        self.search
+            .input
+            .value()
+            .split(',')
+            .any(|t| t.trim() == term)
+    }
+
+    /// Rewrites the tag in every task that has it, an empty `new` deletes the tag.
+    /// The projects nested under a project are renamed along with it
+    fn rename_tag(&mut self, old: &str, new: &str) {
+        let is_project = old.starts_with(PROJECT_PREFIX);
+        let uids: Vec<u64> = self
+            .tasks
+            .iter()
+            .filter(|t| match old.strip_prefix(PROJECT_PREFIX) {
+                Some(project) => t.projects().any(|w| is_subproject(w, project)),
+                None => t.text.split_whitespace().any(|w| w == old),
+            })
+            .map(|t| t.uid)
+            .collect();
+        if uids.is_empty() || old == new {
+            return;
+        }
+        let description = if new.is_empty() {
+            format!("delete {old} from {}", count_tasks(uids.len()))
+        } else {
+            format!("rename {old} to {new} in {}", count_tasks(uids.len()))
+        };
+        self.checkpoint(description);
+        for uid in uids {
+            if let Some(index) = self.task_position(uid) {
+                if is_project {
+                    self.tasks[index].rename_project(old, new);
+                } else {
+                    self.tasks[index].rename_word(old, new);
+                }
+            }
+        }
+        self.refresh();
+    }
+
+    /// remember the current tasks so that the change about to be made can be undone,
+    /// every change of the tasks starts here so it also marks them as changed
+    fn checkpoint(&mut self, description: impl Into<String>) {
+        self.changed();
+        // the redo is dropped, so the archived tasks brought back by an undo stay
+        let tasks = &self.tasks;
+        self.archived
+            .retain(|a| tasks.iter().all(|t| t.uid != a.uid));
+        let entry = self.snapshot(description.into());
+        if self.undo_stack.undo.len() == UNDO_LIMIT {
+            self.undo_stack.undo.remove(0);
+        }
+        self.undo_stack.undo.push(entry);
+        self.undo_stack.redo.clear();
+    }
+
+    fn snapshot(&self, description: String) -> UndoEntry {
+        UndoEntry {
+            description,
+            tasks: self.tasks.clone(),
+        }
+    }
+
+    /// reverts the last change and returns its description
+    fn undo(&mut self) -> Result<String, &'static str> {
+        let entry = self.undo_stack.undo.last().ok_or("Nothing to undo")?;
+        if entry
+            .tasks
+            .iter()
+            .any(|t| self.archive_saved.contains(&t.uid))
+        {
+            return Err("Can't undo archiving tasks which are already saved to the archive file");
+        }
+        let entry = self.undo_stack.undo.pop().ok_or("Nothing to undo")?;
+        let current = self.snapshot(entry.description.clone());
+        self.undo_stack.redo.push(current);
+        Ok(self.restore(entry))
+    }
+
+    /// applies the last undone change again and returns its description
+    fn redo(&mut self) -> Result<String, &'static str> {
+        let entry = self.undo_stack.redo.pop().ok_or("Nothing to redo")?;
+        let current = self.snapshot(entry.description.clone());
+        self.undo_stack.undo.push(current);
+        Ok(self.restore(entry))
+    }
+
+    fn restore(&mut self, entry: UndoEntry) -> String {
+        self.changed();
+        self.tasks = entry.tasks;
+        self.clear_marks();
+        self.refresh();
+        entry.description
+    }
+
+    /// keeps the state derived from the tasks in sync after they were added, removed or reordered
+    fn refresh(&mut self) {
+        if self.config.move_done_to_end {
+            self.first_done_index = self
+                .tasks
+                .iter()
+                .position(|t| t.done)
+                .unwrap_or(self.tasks.len());
+        }
+        self.nums = (0..self.tasks.len()).map(|e| e.to_string()).collect();
+        self.rebuild_sets();
+        self.filter_tasks();
+        let len = self.visible_tasks().len();
+        if let Some(index) = self.list_state.selected() {
+            if len > 0 && index >= len {
+                self.list_state.select(Some(len - 1))
+            }
         }
     }
 
     fn move_done_tasks(&mut self, index: usize) {
         if self.config.move_done_to_end {
             if self.tasks[index].done {
-                // a new task which is marked as done shouldn't be moved
-                if index < self.first_done_index && self.first_done_index != index {
+                // a new task which is marked as done is already after the pending tasks
+                if index < self.first_done_index {
                     self.tasks[index..self.first_done_index].rotate_left(1);
+                    self.first_done_index -= 1
                 }
-                self.first_done_index -= 1
-            } else {
-                if self.first_done_index != index {
-                    self.tasks[self.first_done_index..index + 1].rotate_right(1);
-                }
+            } else if index >= self.first_done_index {
+                self.tasks[self.first_done_index..index + 1].rotate_right(1);
                 self.first_done_index += 1
             }
         };
     }
 
+    /// Builds the list shown while a search is active or subtasks are shown under their parents,
+    /// otherwise the list shows `tasks` as they are
     fn filter_tasks(&mut self) {
         enum FilterKind {
             Positive,
             Negative,
         }
+        self.subtask_progress = subtask_progress(&self.tasks);
+        self.has_subtasks = !self.subtask_progress.is_empty();
+        self.blocked = blocked_tasks(&self.tasks);
+        self.dependency_cycles = dependency_cycles(&self.tasks);
         let value = self.search.input.value();
-        if value.is_empty() {
-            self.filtered_tasks = Vec::new();
+        let matching: Vec<&Task> = if value.is_empty() {
+            self.tasks.iter().collect()
         } else {
             let values: Vec<(FilterKind, &str)> = value
                 .split(",")
@@ -245,39 +1246,82 @@ impl Model {
                     }
                 })
                 .collect();
+            // a known project matches the projects nested under it but not the ones it is a part of
+            let projects: Vec<Option<&str>> = values
+                .iter()
+                .map(|(_, string)| {
+                    string
+                        .strip_prefix(PROJECT_PREFIX)
+                        .filter(|p| self.is_known_project(p))
+                })
+                .collect();
 
-            self.filtered_tasks = self
-                .tasks
+            self.tasks
                 .iter()
                 .filter(|t| {
-                    values.iter().all(|(kind, string)| {
-                        let res = t.text.contains(*string);
-                        match kind {
-                            FilterKind::Positive => res,
-                            FilterKind::Negative => !res,
-                        }
-                    })
+                    values
+                        .iter()
+                        .zip(&projects)
+                        .all(|((kind, string), project)| {
+                            let res = match (project, *string) {
+                                (_, "blocked:yes") => self.blocked.contains(&t.uid),
+                                (_, "blocked:no") => !self.blocked.contains(&t.uid),
+                                (Some(project), _) => {
+                                    t.projects().any(|p| is_subproject(p, project))
+                                }
+                                (None, _) => t.text.contains(*string),
+                            };
+                            match kind {
+                                FilterKind::Positive => res,
+                                FilterKind::Negative => !res,
+                            }
+                        })
                 })
-                .cloned()
-                .collect();
+                .collect()
+        };
+
+        if self.has_subtasks {
+            let rows = task_tree(&matching, &self.collapsed);
+            self.list_depths = rows.iter().map(|r| r.depth).collect();
+            self.filtered_tasks = rows.iter().map(|r| matching[r.index].clone()).collect();
+        } else {
+            self.list_depths = Vec::new();
+            self.filtered_tasks = if value.is_empty() {
+                Vec::new()
+            } else {
+                matching.into_iter().cloned().collect()
+            };
         }
     }
 
-    fn delete_selected_task(&mut self) {
-        if self.search.is_empty() {
-            if let Some(index) = self.list_state.selected() {
-                self.tasks.remove(index);
-            };
-        } else {
-            if let Some(index) = self.list_state.selected() {
-                let text = &self.filtered_tasks[index].text;
-                if let Some(index) = self.tasks.iter().position(|t| t.text == *text) {
-                    self.tasks.remove(index);
-                };
+    /// collapses or expands the subtasks of the selected task
+    fn toggle_collapse(&mut self) {
+        let Some(index) = self.selected_task_index() else {
+            return;
+        };
+        if let Some(id) = self.tasks[index].id() {
+            if self.subtask_progress.contains_key(id) && !self.collapsed.remove(id) {
+                self.collapsed.insert(id.to_string());
             }
             self.filter_tasks();
         }
-        self.nums.pop();
+    }
+
+    /// adds a task with a `p:` key pointing at the parent, giving the parent an id if it has none
+    fn new_subtask(&mut self, value: String, parent_uid: u64) {
+        let Some(index) = self.task_position(parent_uid) else {
+            return;
+        };
+        self.checkpoint("add subtask");
+        let id = match self.tasks[index].id() {
+            Some(id) => id.to_string(),
+            None => {
+                let id = next_id(&self.tasks);
+                self.tasks[index].add_word(&format!("id:{id}"));
+                id
+            }
+        };
+        self.new_task(format!("{} p:{id}", value.trim_end()));
     }
 
     fn save_search(&mut self) {
@@ -287,7 +1331,10 @@ impl Model {
         if !self.config.searches_path.is_empty() {
             let content = self.saved_searches.list.join("\n");
             let path = Path::new(self.config.searches_path.as_str());
-            let _ = write(path, content);
+            if let Err(error) = write(path, content) {
+                self.notices
+                    .error(format!("Failed to save the searches: {error}"));
+            }
         };
     }
 
@@ -300,16 +1347,244 @@ impl Model {
         let completed = list.iter().filter(|t| t.done).count();
         let total = list.len();
         let todo = total - completed;
-        [
+        let mut lines = vec![
             format!("Total tasks:      {total:>5}"),
             format!("Completed Task:   {completed:>5}"),
             format!("Task to do:       {todo:>5}"),
-        ]
-        .join("\n")
-        .to_string()
+        ];
+        // nested projects add up to their parents
+        let projects: Vec<TagEntry> = self
+            .tag_counts()
+            .into_iter()
+            .filter(|e| e.tag.starts_with(PROJECT_PREFIX))
+            .collect();
+        if !projects.is_empty() {
+            lines.push("".to_string());
+            lines.push(format!("{:<24}{:>6}{:>6}", "Projects", "open", "done"));
+            lines.extend(projects.iter().map(|e| {
+                let name = "  ".repeat(e.depth) + &e.tag;
+                format!("{name:<24}{:>6}{:>6}", e.open, e.done)
+            }));
+        }
+        let blocked = list
+            .iter()
+            .filter(|t| self.blocked.contains(&t.uid))
+            .count();
+        if blocked > 0 {
+            lines.insert(3, format!("Blocked tasks:    {blocked:>5}"));
+        }
+        if !self.dependency_cycles.is_empty() {
+            lines.push("".to_string());
+            lines.push("Dependency cycles".to_string());
+            lines.extend(
+                self.dependency_cycles
+                    .iter()
+                    .map(|c| format!("  {}", c.join(" -> "))),
+            );
+        }
+        lines.join("\n")
+    }
+}
+
+/// the tasks of the lines of a todo file and the index of the first done task,
+/// the done tasks are moved to the end when `move_done_to_end` is set
+fn parse_tasks(lines: &[&str], move_done_to_end: bool) -> (Vec<Task>, usize) {
+    let tasks: Vec<Task> = lines
+        .iter()
+        .filter(|e| {
+            let temp = e.trim();
+
+            !(temp.is_empty() || temp == "x")
+        })
+        .map(|a| Task::new(a))
+        .collect();
+    if move_done_to_end {
+        let mut todo_task: Vec<Task> = Vec::with_capacity(tasks.len());
+        let mut incomplete_tasks = Vec::new();
+
+        for task in tasks {
+            if !task.done {
+                todo_task.push(task)
+            } else {
+                incomplete_tasks.push(task)
+            }
+        }
+        let first_done_index = todo_task.len();
+
+        todo_task.append(&mut incomplete_tasks);
+
+        (todo_task, first_done_index)
+    } else {
+        (tasks, usize::MAX)
+    }
+}
+
+/// the line for the task as it's written to the file
+fn task_line(task: &Task) -> String {
+    if task.done {
+        task.text.clone()
+    } else {
+        task.text.strip_prefix(PENDING_PREFIX).unwrap().to_string()
+    }
+}
+
+fn count_tasks(count: usize) -> String {
+    if count == 1 {
+        "1 task".to_string()
+    } else {
+        format!("{count} tasks")
+    }
+}
+
+#[derive(Debug)]
+pub struct UndoEntry {
+    pub description: String,
+    tasks: Vec<Task>,
+}
+
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    pub undo: Vec<UndoEntry>,
+    pub redo: Vec<UndoEntry>,
+}
+
+#[derive(Debug)]
+pub struct SubstituteChange {
+    pub uid: u64,
+    pub old: String,
+    pub new: String,
+}
+
+/// Lines written or pasted to be added as tasks all at once
+#[derive(Debug, Default)]
+pub struct Capture {
+    pub lines: Vec<String>,
+    /// the line being written after `lines`
+    pub input: Input,
+    /// `+project @context` words added to every task
+    pub suffix: Input,
+    pub editing_suffix: bool,
+}
+
+impl Capture {
+    /// adds the pasted lines, the last one stays in the input to be continued
+    fn paste(&mut self, text: &str) {
+        if self.editing_suffix {
+            let suffix = format!(
+                "{} {}",
+                self.suffix.value(),
+                text.replace(['\r', '\n'], " ")
+            );
+            self.suffix = Input::new(suffix.trim_start().to_string());
+            return;
+        }
+        // some terminals paste line breaks as carriage returns
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let mut lines = text.lines();
+        let Some(first) = lines.next() else {
+            return;
+        };
+        let mut current = self.input.value().to_string() + first;
+        for line in lines {
+            self.lines
+                .push(std::mem::replace(&mut current, line.to_string()));
+        }
+        self.input = Input::new(current);
+    }
+}
+
+/// A note read from disk, the text is None when it can't be read
+#[derive(Debug)]
+pub struct NotePreview {
+    pub path: PathBuf,
+    pub text: Option<String>,
+}
+
+/// a yes or no question, the message for the answer is handled once it is answered
+#[derive(Debug)]
+pub struct Confirm {
+    pub prompt: String,
+    pub on_yes: Message,
+    pub on_no: Option<Message>,
+    /// the state to go back to once it's answered, the list when it's None
+    pub previous: Option<Box<AppState>>,
+}
+
+#[derive(Debug)]
+pub struct TagEntry {
+    /// the project or context with its prefix
+    pub tag: String,
+    /// how deep a nested project is, 0 for the top level
+    pub depth: usize,
+    pub open: usize,
+    pub done: usize,
+}
+
+/// Keys typed in the list which don't make a command yet, like a count or the `g` of `gg`
+#[derive(Debug, Default)]
+pub struct Pending {
+    pub count: Option<usize>,
+    pub keys: Vec<KeyChord>,
+    /// a mark command waiting for its letter
+    pub mark: Option<MarkCommand>,
+    /// the keys as they were typed, shown in the status line
+    pub typed: String,
+    /// the tasks a counted action applies to, taken once the action runs
+    pub targets: Option<Vec<u64>>,
+}
+
+impl Pending {
+    pub fn is_empty(&self) -> bool {
+        self.count.is_none() && self.keys.is_empty() && self.mark.is_none()
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkCommand {
+    Set,
+    Jump,
+}
+
+#[derive(Debug)]
+pub struct Sidebar {
+    pub visible: bool,
+    pub list_state: ListState,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Focus {
+    List,
+    Sidebar,
+}
+
+#[derive(Debug)]
+pub struct TagManager {
+    pub entries: Vec<TagEntry>,
+    pub list_state: ListState,
+    /// the tag picked with `m` to be merged into the next selected one
+    pub merge_source: Option<String>,
+    /// the new name while renaming the selected tag
+    pub rename: Option<Input>,
+}
+
+impl TagManager {
+    fn selected_tag(&self) -> Option<String> {
+        let index = self.list_state.selected()?;
+        self.entries.get(index).map(|e| e.tag.clone())
     }
 }
 
+/// the tasks a substitute command will change, shown for confirmation before it is applied
+#[derive(Debug)]
+pub struct SubstitutePreview {
+    pub changes: Vec<SubstituteChange>,
+    pub list_state: ListState,
+}
+
 #[derive(Debug)]
 pub enum AppState {
     Edit(InputState),
@@ -319,6 +1594,11 @@ pub enum AppState {
     Report,
     Help,
     Goto(String),
+    Substitute(SubstitutePreview),
+    Tags(TagManager),
+    Confirm(Confirm),
+    Capture(Capture),
+    Palette(Palette),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -332,6 +1612,10 @@ pub enum InputState {
     Edit,
     NewTask,
     CopyTask,
+    /// words to add to or remove from the targeted tasks
+    Modify,
+    /// a new task under the parent with the uid
+    Subtask(u64),
 }
 
 #[derive(Debug)]
@@ -347,7 +1631,7 @@ pub enum AutoCompleteKind {
     Context,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Message {
     Quit,
     Next,
@@ -364,25 +1648,105 @@ pub enum Message {
     AutoCompleteAppend,
     AutoCompleteMove(KeyEvent),
     SaveFile,
+    /// asks first when there are unsaved changes
     QuitWithoutSave,
+    /// quits without saving and without asking
+    Discard,
     SaveSearch,
     OpenSavedSearchesView,
     HandleSavedSearchKeys(KeyEvent),
     HandlePaste(String),
     ToggleReport,
     ToggleHelp,
-    GotoStart,
+    /// opens the command line with the text already typed
+    GotoStart(String),
     GotoKeyInput(KeyEvent),
+    PreviewSubstitute(Substitute),
+    SubstituteKey(KeyEvent),
+    OpenTags,
+    TagsKey(KeyEvent),
+    ToggleSidebar,
+    ToggleFocus,
+    ToggleDetail,
+    OpenNote,
+    /// opens the capture popup with the lines of the text
+    OpenCapture(String),
+    CaptureKey(KeyEvent),
+    CapturePaste(String),
+    /// opens the selected task, or the whole file when it's true, in the editor
+    OpenEditor(bool),
+    /// the editor exited, the bool is whether it exited successfully
+    EditorClosed(EditorRequest, bool),
+    /// asks whether to complete the open subtasks of the tasks before toggling them
+    CompleteSubtasks(Vec<u64>),
+    ToggleDoneTasks(Vec<u64>),
+    ConfirmKey(KeyEvent),
+    ToggleCollapse,
+    SidebarKey(KeyEvent),
+    SetPriority(Option<char>),
+    RaisePriority,
+    LowerPriority,
+    ToggleMark,
+    MarkRange,
+    MarkAll,
+    ClearMarks,
+    Archive,
+    Undo,
+    Redo,
+    OpenPalette,
+    PaletteKey(KeyEvent),
+    /// an action bound to the keys typed in the list, with the count typed before it
+    KeyAction(Action),
+    /// a digit of the count typed before an action
+    Count(usize),
+    /// a key which starts a longer sequence like the first `g` of `gg`
+    PendingKey(KeyEvent),
+    ClearPending,
+    Top,
+    /// moves to the last task, or to the task with the number
+    Bottom(Option<usize>),
+    /// moves the selection by the rows without wrapping around
+    Scroll(isize),
+    /// moves to the next overdue task, or the previous one when it's false
+    Overdue(bool),
+    AwaitMark(MarkCommand),
+    /// a click on the task at the index of the list
+    ClickTask(usize, ClickTarget),
+    /// moves the selected task by the rows of the list, up when it's negative
+    MoveTask(isize),
+    /// moves the selected task to the position in the list
+    MoveTaskTo(usize),
+    MarkKey(MarkCommand, char),
+    /// sorts the tasks, the done tasks stay at the end with `move_done_to_end`
+    Sort(SortKey),
+    LoadSearch(String),
+}
+
+/// the flag set when rtodo is killed or its terminal is closed, the tasks are saved before quitting
+fn register_signals() -> std::io::Result<Arc<AtomicBool>> {
+    let terminated = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGTERM, Arc::clone(&terminated))?;
+    #[cfg(unix)]
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&terminated))?;
+    Ok(terminated)
 }
 
+/// waits for an event, but only until the next notice expires or the autosave is due
 fn handle_events(model: &Model) -> color_eyre::Result<Option<Message>> {
+    let now = Instant::now();
+    let timeout = [model.notices.next_expiry(now), model.autosave_in(now)]
+        .into_iter()
+        .flatten()
+        .fold(TICK, Duration::min);
+    if !event::poll(timeout)? {
+        return Ok(None);
+    }
     match event::read()? {
-        Event::Key(key) => {
-            if key.kind == event::KeyEventKind::Press {
-                return Ok(handle_key(model, key));
-            }
+        Event::Key(key) if key.kind == event::KeyEventKind::Press => {
+            return Ok(handle_key(model, key));
         }
         Event::Paste(text) => return Ok(handle_paste(model, text)),
+        Event::Mouse(mouse) if model.config.mouse => return Ok(handle_mouse(model, mouse)),
         _ => (),
     }
     Ok(None)
@@ -390,40 +1754,170 @@ fn handle_events(model: &Model) -> color_eyre::Result<Option<Message>> {
 
 fn handle_key(model: &Model, key_event: KeyEvent) -> Option<Message> {
     match model.app_state {
-        AppState::List => match key_event.code {
-            KeyCode::Up | KeyCode::Char('k') => Some(Message::Prev),
-            KeyCode::Down | KeyCode::Char('j') => Some(Message::Next),
-            KeyCode::Char('q') => Some(Message::Quit),
-            KeyCode::Char('Q') => Some(Message::QuitWithoutSave),
-            KeyCode::Char('d') => Some(Message::ToggleDone),
-            KeyCode::Char('x') => Some(Message::DeleteTask),
-            KeyCode::Char('e') => Some(Message::OpenInput(InputState::Edit)),
-            KeyCode::Char('n') => Some(Message::OpenInput(InputState::NewTask)),
-            KeyCode::Char('c') => Some(Message::OpenInput(InputState::CopyTask)),
-            KeyCode::Char('s') => Some(Message::SaveFile),
-            KeyCode::Char('a') => Some(Message::SaveSearch),
-            KeyCode::Char('l') => Some(Message::OpenSavedSearchesView),
-            KeyCode::Char('/') => Some(Message::OpenSearch),
-            KeyCode::Char('r') => Some(Message::ToggleReport),
-            KeyCode::Char('~') => Some(Message::ToggleHelp),
-            KeyCode::Char(':') => Some(Message::GotoStart),
-            _ => None,
-        },
-        AppState::Edit(_) => match key_event.code {
-            KeyCode::Esc => Some(Message::DiscardEditor),
-            _ => Some(Message::EditorKey(key_event)),
+        AppState::List if model.focus == Focus::Sidebar => match model.keymap.action(key_event) {
+            _ if key_event.code == KeyCode::Esc => Some(Message::ToggleFocus),
+            Some(
+                action @ (Action::ToggleFocus
+                | Action::ToggleSidebar
+                | Action::Quit
+                | Action::QuitWithoutSave
+                | Action::Search),
+            ) => action_message(model, action),
+            _ => Some(Message::SidebarKey(key_event)),
         },
+        AppState::List => {
+            let pending = &model.pending;
+            if let Some(command) = pending.mark {
+                return match key_event.code {
+                    KeyCode::Char(c) if c.is_ascii_alphanumeric() => {
+                        Some(Message::MarkKey(command, c))
+                    }
+                    _ => Some(Message::ClearPending),
+                };
+            }
+            let plain = !key_event
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+            if let KeyCode::Char(c @ '0'..='9') = key_event.code {
+                // a count can't start with 0, which is bound to an action
+                if plain && pending.keys.is_empty() && (c != '0' || pending.count.is_some()) {
+                    return Some(Message::Count(c as usize - '0' as usize));
+                }
+            }
+            let mut keys = pending.keys.clone();
+            keys.push(KeyChord::from(key_event));
+            match model.keymap.lookup(&keys) {
+                Lookup::Action(action) => Some(Message::KeyAction(action)),
+                Lookup::Prefix => Some(Message::PendingKey(key_event)),
+                Lookup::None if !pending.is_empty() => Some(Message::ClearPending),
+                // the uppercase letters which aren't bound to an action set the priority
+                Lookup::None => match key_event.code {
+                    KeyCode::Char(c) if c.is_ascii_uppercase() && plain => {
+                        Some(Message::SetPriority(Some(c)))
+                    }
+                    _ => None,
+                },
+            }
+        }
+        AppState::Edit(_) => Some(Message::EditorKey(key_event)),
         AppState::SavedSearches => Some(Message::HandleSavedSearchKeys(key_event)),
         AppState::SearchInput => Some(Message::SearchKeyInput(key_event)),
         AppState::Report => Some(Message::ToggleReport),
         AppState::Help => match key_event.code {
-            KeyCode::Char('~') | KeyCode::Char('q') | KeyCode::Esc => Some(Message::ToggleHelp),
+            KeyCode::Char('q') | KeyCode::Esc => Some(Message::ToggleHelp),
+            _ if model.keymap.is(key_event, Action::Help) => Some(Message::ToggleHelp),
             _ => None,
         },
         AppState::Goto(_) => Some(Message::GotoKeyInput(key_event)),
+        AppState::Substitute(_) => Some(Message::SubstituteKey(key_event)),
+        AppState::Tags(_) => Some(Message::TagsKey(key_event)),
+        AppState::Confirm(_) => Some(Message::ConfirmKey(key_event)),
+        AppState::Capture(_) => Some(Message::CaptureKey(key_event)),
+        AppState::Palette(_) => Some(Message::PaletteKey(key_event)),
     }
 }
 
+/// the message an action of the keymap sends
+pub fn action_message(model: &Model, action: Action) -> Option<Message> {
+    let msg = match action {
+        Action::Prev => Message::Prev,
+        Action::Next => Message::Next,
+        Action::Quit => Message::Quit,
+        Action::QuitWithoutSave => Message::QuitWithoutSave,
+        Action::ToggleDone => Message::ToggleDone,
+        Action::Delete => Message::DeleteTask,
+        Action::Edit => Message::OpenInput(InputState::Edit),
+        Action::EditInEditor => Message::OpenEditor(false),
+        Action::EditFile => Message::OpenEditor(true),
+        Action::NewTask => Message::OpenInput(InputState::NewTask),
+        Action::Capture => Message::OpenCapture(String::new()),
+        Action::CopyTask => Message::OpenInput(InputState::CopyTask),
+        Action::Subtask => Message::OpenInput(InputState::Subtask(model.selected_uid()?)),
+        Action::Save => Message::SaveFile,
+        Action::SaveSearch => Message::SaveSearch,
+        Action::LoadSearch => Message::OpenSavedSearchesView,
+        Action::Search => Message::OpenSearch,
+        Action::Undo => Message::Undo,
+        Action::Redo => Message::Redo,
+        Action::Report => Message::ToggleReport,
+        Action::Help => Message::ToggleHelp,
+        Action::CommandLine => Message::GotoStart(String::new()),
+        Action::RaisePriority => Message::RaisePriority,
+        Action::LowerPriority => Message::LowerPriority,
+        Action::ClearPriority => Message::SetPriority(None),
+        Action::Mark => Message::ToggleMark,
+        Action::MarkRange => Message::MarkRange,
+        Action::MarkAll => Message::MarkAll,
+        Action::ClearMarks => Message::ClearMarks,
+        Action::Modify => Message::OpenInput(InputState::Modify),
+        Action::Archive => Message::Archive,
+        Action::Tags => Message::OpenTags,
+        Action::ToggleSidebar => Message::ToggleSidebar,
+        Action::ToggleDetail => Message::ToggleDetail,
+        Action::Note => Message::OpenNote,
+        Action::ToggleFocus => Message::ToggleFocus,
+        Action::ToggleCollapse => Message::ToggleCollapse,
+        Action::Palette => Message::OpenPalette,
+        Action::Top => Message::Top,
+        Action::Bottom => Message::Bottom(None),
+        Action::HalfPageDown => Message::Scroll(model.page_size() as isize / 2),
+        Action::HalfPageUp => Message::Scroll(-(model.page_size() as isize / 2)),
+        Action::PageDown => Message::Scroll(model.page_size() as isize),
+        Action::PageUp => Message::Scroll(-(model.page_size() as isize)),
+        Action::NextOverdue => Message::Overdue(true),
+        Action::PrevOverdue => Message::Overdue(false),
+        Action::SetMark => Message::AwaitMark(MarkCommand::Set),
+        Action::JumpToMark => Message::AwaitMark(MarkCommand::Jump),
+        Action::MoveUp => Message::MoveTask(-1),
+        Action::MoveDown => Message::MoveTask(1),
+        Action::MoveToTop => Message::MoveTaskTo(0),
+        Action::MoveToBottom => Message::MoveTaskTo(usize::MAX),
+    };
+    Some(msg)
+}
+
+/// every action of the command palette with the keys bound to it
+fn palette_entries(model: &Model) -> Vec<PaletteEntry> {
+    let mut entries: Vec<PaletteEntry> = Action::ALL
+        .into_iter()
+        .filter(|action| *action != Action::Palette)
+        .filter_map(|action| {
+            Some(PaletteEntry {
+                label: action.description().to_string(),
+                keys: model.keymap.key_names(action),
+                message: action_message(model, action)?,
+            })
+        })
+        .collect();
+    entries.push(PaletteEntry {
+        label: "Switch to another todo file".to_string(),
+        keys: ":open".to_string(),
+        message: Message::GotoStart("open ".to_string()),
+    });
+    entries.extend(SortKey::ALL.into_iter().map(|key| PaletteEntry {
+        label: format!("Sort by {}", key.name()),
+        keys: String::new(),
+        message: Message::Sort(key),
+    }));
+    entries.extend(model.saved_searches.list.iter().map(|search| PaletteEntry {
+        label: format!("Load search {search}"),
+        keys: String::new(),
+        message: Message::LoadSearch(search.clone()),
+    }));
+    entries.extend(('A'..='Z').map(|priority| {
+        let key = KeyEvent::from(KeyCode::Char(priority));
+        PaletteEntry {
+            label: format!("Set priority ({priority})"),
+            keys: match model.keymap.action(key) {
+                Some(_) => String::new(),
+                None => priority.to_string(),
+            },
+            message: Message::SetPriority(Some(priority)),
+        }
+    }));
+    entries
+}
+
 fn update(model: &mut Model, msg: Message) -> Option<Message> {
     match msg {
         Message::Quit => {
@@ -431,7 +1925,8 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             None
         }
         Message::Next => {
-            list_next_cyclic(&mut model.list_state, model.tasks.len());
+            let len = model.visible_tasks().len();
+            list_next_cyclic(&mut model.list_state, len);
             None
         }
         Message::Prev => {
@@ -439,19 +1934,104 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             None
         }
         Message::ToggleDone => {
-            model.update_task(true);
+            let uids = model.target_uids();
+            let reopening: Vec<u64> = model
+                .tasks
+                .iter()
+                .filter(|t| t.done && uids.contains(&t.uid))
+                .map(|t| t.uid)
+                .collect();
+            let dependents = dependents(&model.tasks, &reopening);
+            let blocked = uids
+                .iter()
+                .filter(|uid| model.blocked.contains(uid))
+                .count();
+            let warning = if !dependents.is_empty() {
+                Some(format!(
+                    "Reopening blocks {} again, reopen anyway?",
+                    count_tasks(dependents.len())
+                ))
+            } else if blocked > 0 {
+                Some(format!(
+                    "{} still waiting on dependencies, complete anyway?",
+                    count_tasks(blocked)
+                ))
+            } else {
+                None
+            };
+            match warning {
+                Some(prompt) => {
+                    model.app_state = AppState::Confirm(Confirm {
+                        prompt,
+                        on_yes: Message::CompleteSubtasks(uids),
+                        on_no: None,
+                        previous: None,
+                    });
+                    None
+                }
+                None => Some(Message::CompleteSubtasks(uids)),
+            }
+        }
+        Message::CompleteSubtasks(uids) => {
+            let completing: Vec<u64> = model
+                .tasks
+                .iter()
+                .filter(|t| !t.done && uids.contains(&t.uid))
+                .map(|t| t.uid)
+                .collect();
+            let open_subtasks: Vec<u64> = if model.config.prompt_complete_subtasks {
+                descendants(&model.tasks, &completing)
+                    .into_iter()
+                    .filter(|uid| {
+                        model
+                            .task_position(*uid)
+                            .is_some_and(|i| !model.tasks[i].done)
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            if open_subtasks.is_empty() {
+                Some(Message::ToggleDoneTasks(uids))
+            } else {
+                model.app_state = AppState::Confirm(Confirm {
+                    prompt: format!("Also complete {} open subtasks?", open_subtasks.len()),
+                    on_yes: Message::ToggleDoneTasks([uids.clone(), open_subtasks].concat()),
+                    on_no: Some(Message::ToggleDoneTasks(uids)),
+                    previous: None,
+                });
+                None
+            }
+        }
+        Message::ToggleDoneTasks(uids) => {
+            model.toggle_done(uids);
+            None
+        }
+        Message::ConfirmKey(key_event) => {
+            let AppState::Confirm(ref mut confirm) = model.app_state else {
+                return None;
+            };
+            let msg = match key_event.code {
+                KeyCode::Char('y') | KeyCode::Enter => Some(confirm.on_yes.clone()),
+                KeyCode::Char('n') => confirm.on_no.clone(),
+                KeyCode::Esc | KeyCode::Char('q') => None,
+                _ => return None,
+            };
+            let previous = confirm.previous.take();
+            model.app_state = previous.map_or(AppState::List, |state| *state);
+            msg
+        }
+        Message::ToggleCollapse => {
+            model.toggle_collapse();
             None
         }
         Message::OpenInput(input_state) => {
+            model.task_history.reset();
+            model.vim.reset();
             match input_state {
                 InputState::Edit => {
                     if let Some(index) = model.list_state.selected() {
-                        let list = if !model.search.is_empty() {
-                            &model.filtered_tasks
-                        } else {
-                            &model.tasks
-                        };
-                        if let Some(value) = list.get(index) {
+                        if let Some(value) = model.visible_tasks().get(index) {
                             let value = if value.done {
                                 value.text.clone()
                             } else {
@@ -462,7 +2042,7 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
                     };
                     model.app_state = AppState::Edit(input_state);
                 }
-                InputState::NewTask => {
+                InputState::NewTask | InputState::Subtask(_) => {
                     let base = if model.config.add_creation_date {
                         let local = Local::now();
                         local.format_with_items(DATE_FORMAT_CONST).to_string()
@@ -472,14 +2052,13 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
                     model.input = Input::new(base + " ");
                     model.app_state = AppState::Edit(input_state);
                 }
+                InputState::Modify => {
+                    model.input = Input::default();
+                    model.app_state = AppState::Edit(input_state);
+                }
                 InputState::CopyTask => {
                     if let Some(index) = model.list_state.selected() {
-                        let list = if !model.search.is_empty() {
-                            &model.filtered_tasks
-                        } else {
-                            &model.tasks
-                        };
-                        if let Some(value) = list.get(index) {
+                        if let Some(value) = model.visible_tasks().get(index) {
                             let value = if value.done {
                                 value.text.clone()
                             } else {
@@ -494,49 +2073,99 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             None
         }
         Message::InputAction(input_state) => {
-            if model.input.value().trim().is_empty() {
-                model.delete_selected_task()
+            if let Err(error) = model.task_history.push(model.input.value()) {
+                model
+                    .notices
+                    .warn(format!("Failed to keep the task history: {error}"));
+            }
+            if input_state == InputState::Modify {
+                let value = model.input.value().to_string();
+                let uids = model.target_uids();
+                model.modify_tasks("modify", uids, |task| {
+                    for word in value.split_whitespace() {
+                        match word.strip_prefix('-') {
+                            Some(word) if !word.is_empty() => task.remove_word(word),
+                            _ => task.add_word(word),
+                        }
+                    }
+                });
+            } else if model.input.value().trim().is_empty() {
+                // clearing the text of a task deletes it
+                if input_state == InputState::Edit {
+                    let uids = model.selected_uid().into_iter().collect();
+                    model.delete_tasks(uids)
+                }
             } else {
                 match input_state {
                     InputState::Edit => {
-                        model.update_task(false);
+                        model.update_task();
                     }
                     InputState::NewTask | InputState::CopyTask => {
                         let value = model.input.value().to_string();
+                        model.checkpoint("add task");
                         model.new_task(value);
                     }
+                    InputState::Subtask(parent_uid) => {
+                        let value = model.input.value().to_string();
+                        model.new_subtask(value, parent_uid);
+                    }
+                    InputState::Modify => unreachable!(),
                 };
             }
             model.app_state = AppState::List;
             None
         }
-        Message::EditorKey(event) => match event.code {
-            KeyCode::Enter => {
-                if model.auto_complete.is_some() {
-                    Some(Message::AutoCompleteAppend)
-                } else if let AppState::Edit(ref input_state) = model.app_state {
-                    Some(Message::InputAction(input_state.clone()))
-                } else {
+        Message::EditorKey(event) => {
+            if model.config.vim_mode
+                && model
+                    .vim
+                    .handle_key(&mut model.input, event, &mut model.kill_buffer)
+            {
+                return model.vim_autocomplete();
+            }
+            match event.code {
+                KeyCode::Esc => Some(Message::DiscardEditor),
+                KeyCode::Enter => {
+                    if model.auto_complete.is_some() {
+                        Some(Message::AutoCompleteAppend)
+                    } else if let AppState::Edit(ref input_state) = model.app_state {
+                        Some(Message::InputAction(input_state.clone()))
+                    } else {
+                        None
+                    }
+                }
+                KeyCode::Tab => Some(Message::AutoCompleteMove(event)),
+                KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    model.input = Input::default();
                     None
                 }
+                KeyCode::Up | KeyCode::Down => {
+                    let entry = if event.code == KeyCode::Up {
+                        model.task_history.prev(model.input.value())
+                    } else {
+                        model.task_history.next()
+                    };
+                    if let Some(entry) = entry {
+                        model.input = Input::new(entry.to_string());
+                        model.auto_complete = None;
+                    }
+                    None
+                }
+                _ => {
+                    readline::handle_key(&mut model.input, event, &mut model.kill_buffer);
+                    Some(Message::HandleAutoComplete)
+                }
             }
-            KeyCode::Tab => Some(Message::AutoCompleteMove(event)),
-            KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                model.input = Input::default();
-                None
-            }
-            _ => {
-                model.input.handle_event(&Event::Key(event));
-                Some(Message::HandleAutoComplete)
-            }
-        },
+        }
         Message::DiscardEditor => {
+            model.pending.targets = None;
             model.app_state = AppState::List;
             model.auto_complete = None;
             None
         }
         Message::DeleteTask => {
-            model.delete_selected_task();
+            let uids = model.target_uids();
+            model.delete_tasks(uids);
             None
         }
         Message::HandleAutoComplete => {
@@ -552,12 +2181,15 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
                 } else if let Some(last_word) = before.split_whitespace().last() {
                     if last_word.starts_with(PROJECT_PREFIX) {
                         let match_word = last_word.strip_prefix(PROJECT_PREFIX).unwrap();
-                        let suggestions = model
+                        // nested projects are completed one segment at a time
+                        let mut suggestions = model
                             .projects
                             .iter()
-                            .filter(|s| s.contains(match_word))
-                            .cloned()
+                            .filter_map(|s| complete_project(s, match_word))
+                            .collect::<HashSet<String>>()
+                            .into_iter()
                             .collect::<Vec<String>>();
+                        suggestions.sort();
                         if !suggestions.is_empty() {
                             model.auto_complete = Some(Autocomplete {
                                 kind: AutoCompleteKind::Project,
@@ -588,50 +2220,111 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             None
         }
         Message::SaveFile => {
-            model.write().ok();
+            if !model.dirty {
+                model.notices.info("No changes to save");
+                return None;
+            }
+            match model.write() {
+                Ok(()) => model.notices.info(format!(
+                    "Saved {} to {}",
+                    count_tasks(model.tasks.len()),
+                    model.config.file_path
+                )),
+                Err(error) => model.notices.error(format!("Failed to save: {error}")),
+            }
             None
         }
         Message::QuitWithoutSave => {
+            if model.dirty {
+                model.app_state = AppState::Confirm(Confirm {
+                    prompt: "Quit and discard the unsaved changes?".to_string(),
+                    on_yes: Message::Discard,
+                    on_no: None,
+                    previous: None,
+                });
+                None
+            } else {
+                Some(Message::Discard)
+            }
+        }
+        Message::Discard => {
             model.save_file = false;
             model.live_state = LiveState::Done;
             None
         }
         Message::OpenSearch => {
+            model.search_history.reset();
+            model.vim.reset();
             model.search.prev_value = model.search.input.value().to_string();
             model.app_state = AppState::SearchInput;
             None
         }
-        Message::SearchKeyInput(key_event) => match key_event.code {
-            KeyCode::Enter => {
-                if model.auto_complete.is_some() {
-                    Some(Message::AutoCompleteAppend)
-                } else {
+        Message::SearchKeyInput(key_event) => {
+            if model.config.vim_mode
+                && model
+                    .vim
+                    .handle_key(&mut model.search.input, key_event, &mut model.kill_buffer)
+            {
+                model.filter_tasks();
+                return model.vim_autocomplete();
+            }
+            match key_event.code {
+                KeyCode::Enter => {
+                    if model.auto_complete.is_some() {
+                        Some(Message::AutoCompleteAppend)
+                    } else {
+                        // the history is only a convenience, failing to keep it is just a warning
+                        if let Err(error) = model.search_history.push(model.search.input.value()) {
+                            model
+                                .notices
+                                .warn(format!("Failed to keep the search history: {error}"));
+                        }
+                        model.app_state = AppState::List;
+                        model.auto_complete = None;
+                        None
+                    }
+                }
+                KeyCode::Esc => {
                     model.app_state = AppState::List;
+                    if !model.search.prev_value.is_empty() {
+                        model.search.input = Input::new(model.search.prev_value.clone());
+                    }
+                    model.filter_tasks();
                     model.auto_complete = None;
                     None
                 }
-            }
-            KeyCode::Esc => {
-                model.app_state = AppState::List;
-                if !model.search.prev_value.is_empty() {
-                    model.search.input = Input::new(model.search.prev_value.clone());
+                KeyCode::Tab => Some(Message::AutoCompleteMove(key_event)),
+                KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    let value = model.search.input.value();
+                    model.search.prev_value = value.to_string();
+                    model.search.input = Input::default();
+                    model.filter_tasks();
+                    None
+                }
+                KeyCode::Up | KeyCode::Down => {
+                    let entry = if key_event.code == KeyCode::Up {
+                        model.search_history.prev(model.search.input.value())
+                    } else {
+                        model.search_history.next()
+                    };
+                    if let Some(entry) = entry {
+                        model.search.input = Input::new(entry.to_string());
+                        model.auto_complete = None;
+                        model.filter_tasks();
+                    }
+                    None
+                }
+                _ => {
+                    readline::handle_key(
+                        &mut model.search.input,
+                        key_event,
+                        &mut model.kill_buffer,
+                    );
+                    model.filter_tasks();
+                    Some(Message::HandleAutoComplete)
                 }
-                model.auto_complete = None;
-                None
-            }
-            KeyCode::Tab => Some(Message::AutoCompleteMove(key_event)),
-            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                let value = model.search.input.value();
-                model.search.prev_value = value.to_string();
-                model.search.input = Input::default();
-                None
-            }
-            _ => {
-                model.search.input.handle_event(&Event::Key(key_event));
-                model.filter_tasks();
-                Some(Message::HandleAutoComplete)
             }
-        },
+        }
         Message::AutoCompleteAppend => {
             if let Some(ac) = model.auto_complete.as_ref() {
                 if let Some(index) = ac.list_state.selected() {
@@ -682,6 +2375,224 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             model.saved_searches.list_state.select(Some(0));
             None
         }
+        Message::OpenPalette => {
+            model.app_state = AppState::Palette(Palette::new(palette_entries(model)));
+            None
+        }
+        Message::PaletteKey(key_event) => {
+            let AppState::Palette(ref mut palette) = model.app_state else {
+                return None;
+            };
+            let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+            let len = palette.matches().len();
+            match key_event.code {
+                KeyCode::Esc => model.app_state = AppState::List,
+                KeyCode::Enter => {
+                    let message = palette.selected().map(|entry| entry.message.clone());
+                    model.app_state = AppState::List;
+                    return message;
+                }
+                KeyCode::Up => list_prev_cyclic(&mut palette.list_state),
+                KeyCode::Char('p') if ctrl => list_prev_cyclic(&mut palette.list_state),
+                KeyCode::Down => list_next_cyclic(&mut palette.list_state, len),
+                KeyCode::Char('n') if ctrl => list_next_cyclic(&mut palette.list_state, len),
+                _ => {
+                    readline::handle_key(&mut palette.input, key_event, &mut model.kill_buffer);
+                    palette.list_state.select(Some(0));
+                }
+            }
+            None
+        }
+        Message::KeyAction(action) => {
+            let count = model.pending.count;
+            model.pending.clear();
+            let msg = action_message(model, action)?;
+            let Some(count) = count else {
+                return Some(msg);
+            };
+            match action {
+                // like vim 5G and 5gg go to the task with the number
+                Action::Top | Action::Bottom => Some(Message::Bottom(Some(count))),
+                Action::MoveUp => Some(Message::MoveTask(-model.visible_steps(count))),
+                Action::MoveDown => Some(Message::MoveTask(model.visible_steps(count))),
+                // motions move once by the whole distance, stopping at the ends of the list
+                Action::Next => Some(Message::Scroll(model.visible_steps(count))),
+                Action::Prev => Some(Message::Scroll(-model.visible_steps(count))),
+                Action::HalfPageDown | Action::HalfPageUp | Action::PageDown | Action::PageUp => {
+                    let Message::Scroll(rows) = msg else {
+                        return Some(msg);
+                    };
+                    Some(Message::Scroll(
+                        rows.saturating_mul(model.visible_steps(count)),
+                    ))
+                }
+                Action::NextOverdue
+                | Action::PrevOverdue
+                | Action::Mark
+                | Action::Undo
+                | Action::Redo => {
+                    // more steps than there are tasks or changes don't do anything more
+                    let limit = match action {
+                        Action::Undo => model.undo_stack.undo.len(),
+                        Action::Redo => model.undo_stack.redo.len(),
+                        _ => model.visible_tasks().len(),
+                    };
+                    for _ in 0..count.min(limit.max(1)) {
+                        let mut current = Some(msg.clone());
+                        while let Some(msg) = current {
+                            current = update(model, msg);
+                        }
+                    }
+                    None
+                }
+                // the actions on the marked tasks act on the tasks from the selected one
+                Action::ToggleDone
+                | Action::Delete
+                | Action::Modify
+                | Action::Archive
+                | Action::RaisePriority
+                | Action::LowerPriority
+                | Action::ClearPriority => {
+                    if model.marked.is_empty() && model.mark_anchor.is_none() {
+                        let start = model.list_state.selected().unwrap_or(0);
+                        let uids: Vec<u64> = model
+                            .visible_tasks()
+                            .iter()
+                            .skip(start)
+                            .take(count)
+                            .map(|t| t.uid)
+                            .collect();
+                        model.pending.targets = Some(uids);
+                    }
+                    Some(msg)
+                }
+                _ => Some(msg),
+            }
+        }
+        Message::Count(digit) => {
+            let count = model.pending.count.unwrap_or(0);
+            model.pending.count = Some(count.saturating_mul(10).saturating_add(digit));
+            model.pending.typed.push_str(&digit.to_string());
+            None
+        }
+        Message::PendingKey(key_event) => {
+            let chord = KeyChord::from(key_event);
+            model.pending.typed.push_str(&chord.to_string());
+            model.pending.keys.push(chord);
+            None
+        }
+        Message::ClearPending => {
+            model.pending.clear();
+            None
+        }
+        Message::Top => {
+            model.list_state.select(Some(0));
+            None
+        }
+        Message::Bottom(num) => {
+            let len = model.visible_tasks().len();
+            let index = num.unwrap_or(usize::MAX).min(len.saturating_sub(1));
+            model.list_state.select(Some(index));
+            None
+        }
+        Message::Scroll(rows) => {
+            let len = model.visible_tasks().len();
+            let index = model.list_state.selected().unwrap_or(0) as isize + rows;
+            let index = index.clamp(0, len.saturating_sub(1) as isize);
+            model.list_state.select(Some(index as usize));
+            None
+        }
+        Message::Overdue(forward) => {
+            match model.overdue_index(forward) {
+                Some(index) => model.list_state.select(Some(index)),
+                None => model.notices.error("No overdue tasks in the list"),
+            }
+            None
+        }
+        Message::MoveTask(steps) => {
+            let selected = model.list_state.selected().unwrap_or(0);
+            let siblings = model.sibling_rows(selected);
+            if let (Some(uid), Some(position)) = (
+                model.selected_uid(),
+                siblings.iter().position(|row| *row == selected),
+            ) {
+                let to = (position as isize + steps).clamp(0, siblings.len() as isize - 1);
+                model.move_task(uid, siblings[to as usize]);
+            }
+            None
+        }
+        Message::MoveTaskTo(to) => {
+            if let Some(uid) = model.selected_uid() {
+                model.move_task(uid, to);
+            }
+            None
+        }
+        Message::ClickTask(index, target) => {
+            model.focus = Focus::List;
+            let double = model
+                .last_click
+                .is_some_and(|(time, last)| last == index && time.elapsed() < DOUBLE_CLICK);
+            model.last_click = Some((Instant::now(), index));
+            model.list_state.select(Some(index));
+            match target {
+                ClickTarget::Checkbox => Some(Message::ToggleDone),
+                ClickTarget::Tag(tag) => Some(Message::LoadSearch(tag)),
+                ClickTarget::Task if double => {
+                    model.last_click = None;
+                    Some(Message::OpenInput(InputState::Edit))
+                }
+                ClickTarget::Task => None,
+            }
+        }
+        Message::AwaitMark(command) => {
+            model.pending.mark = Some(command);
+            let action = match command {
+                MarkCommand::Set => Action::SetMark,
+                MarkCommand::Jump => Action::JumpToMark,
+            };
+            if let Some(keys) = model.keymap.keys(action).first() {
+                model.pending.typed.push_str(&keys.to_string());
+            }
+            None
+        }
+        Message::MarkKey(command, letter) => {
+            model.pending.clear();
+            match command {
+                MarkCommand::Set => {
+                    if let Some(uid) = model.selected_uid() {
+                        model.jump_marks.insert(letter, uid);
+                    }
+                }
+                MarkCommand::Jump => {
+                    let uid = model
+                        .jump_marks
+                        .get(&letter)
+                        .copied()
+                        .filter(|uid| model.task_position(*uid).is_some());
+                    match uid {
+                        Some(uid) if model.visible_tasks().iter().any(|t| t.uid == uid) => {
+                            model.select_uid(uid)
+                        }
+                        Some(_) => model
+                            .notices
+                            .error(format!("The task of mark {letter} isn't listed")),
+                        None => model.notices.error(format!("Mark {letter} isn't set")),
+                    }
+                }
+            }
+            None
+        }
+        Message::Sort(key) => {
+            model.sort_tasks(key);
+            None
+        }
+        Message::LoadSearch(search) => {
+            model.app_state = AppState::List;
+            model.search.input = Input::new(search);
+            model.filter_tasks();
+            model.list_state.select(Some(0));
+            None
+        }
         Message::HandleSavedSearchKeys(key_event) => {
             match key_event.code {
                 KeyCode::Up | KeyCode::Char('k') => {
@@ -709,21 +2620,68 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             None
         }
         Message::HandlePaste(text) => {
+            // some terminals paste line breaks as carriage returns
+            let text = text.replace("\r\n", "\n").replace('\r', "\n");
+            let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
             let input = match model.app_state {
                 AppState::Edit(_) => &model.input,
                 AppState::SearchInput => &model.search.input,
+                AppState::Tags(ref manager) => match manager.rename {
+                    Some(ref input) => input,
+                    None => return None,
+                },
+                AppState::Goto(ref mut value) => {
+                    value.push_str(&lines.join(" "));
+                    model.update_command_completions();
+                    return None;
+                }
+                AppState::Palette(ref palette) => &palette.input,
                 _ => {
                     return None;
                 }
             };
+            // the cursor counts chars, not bytes
             let cursor = input.cursor();
-            let (before, after) = input.value().split_at(cursor);
+            let split = input
+                .value()
+                .char_indices()
+                .nth(cursor)
+                .map_or(input.value().len(), |(i, _)| i);
+            let (before, after) = input.value().split_at(split);
+
+            // several lines pasted in a new task can be added as a task each
+            if lines.len() > 1
+                && matches!(
+                    model.app_state,
+                    AppState::Edit(InputState::NewTask | InputState::CopyTask)
+                )
+            {
+                let tasks = before.to_string() + &text + after;
+                let previous = std::mem::replace(&mut model.app_state, AppState::List);
+                model.app_state = AppState::Confirm(Confirm {
+                    prompt: format!("Add the {} pasted lines as separate tasks?", lines.len()),
+                    on_yes: Message::OpenCapture(tasks),
+                    on_no: Some(Message::HandlePaste(lines.join(" "))),
+                    previous: Some(Box::new(previous)),
+                });
+                return None;
+            }
+
+            let text = lines.join(" ");
             let new = before.to_string() + &text + after;
-            let new_cursor = cursor + text.len();
+            let new_cursor = cursor + text.chars().count();
             let new_input = Input::default().with_value(new).with_cursor(new_cursor);
             match model.app_state {
                 AppState::Edit(_) => model.input = new_input,
-                AppState::SearchInput => model.search.input = new_input,
+                AppState::SearchInput => {
+                    model.search.input = new_input;
+                    model.filter_tasks();
+                }
+                AppState::Tags(ref mut manager) => manager.rename = Some(new_input),
+                AppState::Palette(ref mut palette) => {
+                    palette.input = new_input;
+                    palette.list_state.select(Some(0));
+                }
                 _ => return None,
             };
             None
@@ -746,36 +2704,357 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             };
             None
         }
-        Message::GotoStart => {
-            model.app_state = AppState::Goto("".to_string());
+        Message::GotoStart(command) => {
+            model.app_state = AppState::Goto(command);
+            model.update_command_completions();
             None
         }
         Message::GotoKeyInput(key_event) => {
             match key_event.code {
-                KeyCode::Char(d) if d.is_ascii_digit() => {
+                KeyCode::Char(c) => {
                     if let AppState::Goto(ref mut str) = model.app_state {
-                        str.push(d)
+                        str.push(c)
                     };
                 }
-                KeyCode::Enter => {
-                    if let AppState::Goto(ref str) = model.app_state {
-                        if let Ok(num) = str.parse::<usize>() {
-                            model.list_state.select(Some(num))
+                KeyCode::Backspace => {
+                    if let AppState::Goto(ref mut str) = model.app_state {
+                        if str.pop().is_none() {
+                            model.app_state = AppState::List;
                         }
+                    };
+                }
+                KeyCode::Tab => {
+                    if let Some(prefix) = command::common_prefix(&model.command_completions) {
+                        model.app_state = AppState::Goto(prefix);
                     }
+                }
+                KeyCode::Enter => {
+                    let command = match model.app_state {
+                        AppState::Goto(ref str) => str.clone(),
+                        _ => String::new(),
+                    };
                     model.app_state = AppState::List;
+                    if command.trim().is_empty() {
+                        return None;
+                    }
+                    match Command::parse(&command) {
+                        Ok(command) => return model.run_command(command),
+                        Err(error) => model.notices.error(error),
+                    }
                 }
                 KeyCode::Esc => {
                     model.app_state = AppState::List;
                 }
                 _ => {}
             }
+            model.update_command_completions();
+            None
+        }
+        Message::PreviewSubstitute(substitute) => {
+            let changes = model.substitute_changes(&substitute);
+            if changes.is_empty() {
+                model
+                    .notices
+                    .error(format!("Pattern not found: {}", substitute.pattern));
+            } else {
+                model.app_state = AppState::Substitute(SubstitutePreview {
+                    changes,
+                    list_state: ListState::default().with_selected(Some(0)),
+                });
+            }
+            None
+        }
+        Message::SubstituteKey(key_event) => {
+            if let AppState::Substitute(ref mut preview) = model.app_state {
+                match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => preview.list_state.select_previous(),
+                    KeyCode::Down | KeyCode::Char('j') => preview.list_state.select_next(),
+                    KeyCode::Enter | KeyCode::Char('y') => {
+                        let changes = std::mem::take(&mut preview.changes);
+                        model.apply_substitute(&changes);
+                        model.app_state = AppState::List;
+                    }
+                    KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                        model.app_state = AppState::List;
+                    }
+                    _ => {}
+                }
+            }
+            None
+        }
+        Message::OpenTags => {
+            model.app_state = AppState::Tags(TagManager {
+                entries: model.tag_counts(),
+                list_state: ListState::default().with_selected(Some(0)),
+                merge_source: None,
+                rename: None,
+            });
+            None
+        }
+        Message::TagsKey(key_event) => {
+            let AppState::Tags(ref mut manager) = model.app_state else {
+                return None;
+            };
+            // the old and new tag of a rename, merge or delete to apply
+            let mut change: Option<(String, String)> = None;
+            if let Some(ref mut input) = manager.rename {
+                match key_event.code {
+                    KeyCode::Enter => {
+                        let value = input.value().trim().to_string();
+                        if let Some(old) = manager.selected_tag() {
+                            if !value.is_empty() && !value.contains(char::is_whitespace) {
+                                let new = if value.starts_with(PROJECT_PREFIX)
+                                    || value.starts_with(CONTEXT_PREFIX)
+                                {
+                                    value
+                                } else {
+                                    old[..1].to_string() + &value
+                                };
+                                change = Some((old, new));
+                            }
+                        }
+                        manager.rename = None;
+                    }
+                    KeyCode::Esc => manager.rename = None,
+                    _ => readline::handle_key(input, key_event, &mut model.kill_buffer),
+                }
+            } else {
+                match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => manager.list_state.select_previous(),
+                    KeyCode::Down | KeyCode::Char('j') => manager.list_state.select_next(),
+                    KeyCode::Char('r') => {
+                        if let Some(tag) = manager.selected_tag() {
+                            manager.rename = Some(Input::new(tag));
+                        }
+                    }
+                    KeyCode::Char('m') | KeyCode::Enter => match manager.merge_source.take() {
+                        Some(source) => {
+                            if let Some(target) = manager.selected_tag() {
+                                change = Some((source, target));
+                            }
+                        }
+                        None if key_event.code != KeyCode::Enter => {
+                            manager.merge_source = manager.selected_tag()
+                        }
+                        None => {}
+                    },
+                    KeyCode::Char('x') | KeyCode::Delete => {
+                        if let Some(tag) = manager.selected_tag() {
+                            change = Some((tag, String::new()));
+                        }
+                    }
+                    KeyCode::Esc if manager.merge_source.is_some() => manager.merge_source = None,
+                    _ if key_event.code == KeyCode::Esc
+                        || key_event.code == KeyCode::Char('q')
+                        || model.keymap.action(key_event) == Some(Action::Tags) =>
+                    {
+                        model.app_state = AppState::List;
+                        return None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some((old, new)) = change {
+                model.rename_tag(&old, &new);
+                let entries = model.tag_counts();
+                if let AppState::Tags(ref mut manager) = model.app_state {
+                    let selected = manager.list_state.selected().unwrap_or(0);
+                    manager
+                        .list_state
+                        .select(Some(selected.min(entries.len().saturating_sub(1))));
+                    manager.entries = entries;
+                }
+            }
+            None
+        }
+        Message::OpenEditor(whole_file) => {
+            model.open_in_editor(whole_file);
+            None
+        }
+        Message::OpenCapture(text) => {
+            let mut capture = Capture::default();
+            capture.paste(&text);
+            model.app_state = AppState::Capture(capture);
+            None
+        }
+        Message::CapturePaste(text) => {
+            if let AppState::Capture(ref mut capture) = model.app_state {
+                capture.paste(&text);
+            }
+            None
+        }
+        Message::CaptureKey(key_event) => {
+            let AppState::Capture(ref mut capture) = model.app_state else {
+                return None;
+            };
+            let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+            match key_event.code {
+                KeyCode::Esc => model.app_state = AppState::List,
+                KeyCode::Char('s') if ctrl => {
+                    let mut lines = std::mem::take(&mut capture.lines);
+                    lines.push(capture.input.value().to_string());
+                    let suffix = capture.suffix.value().to_string();
+                    model.app_state = AppState::List;
+                    model.add_tasks(&lines, &suffix);
+                }
+                KeyCode::Tab => capture.editing_suffix = !capture.editing_suffix,
+                _ if capture.editing_suffix => {
+                    capture.suffix.handle_event(&Event::Key(key_event));
+                }
+                KeyCode::Enter => {
+                    let line = capture.input.value().to_string();
+                    capture.lines.push(line);
+                    capture.input = Input::default();
+                }
+                // going back from an empty line continues the line before it
+                KeyCode::Backspace if capture.input.value().is_empty() => {
+                    if let Some(line) = capture.lines.pop() {
+                        capture.input = Input::new(line);
+                    }
+                }
+                _ => {
+                    capture.input.handle_event(&Event::Key(key_event));
+                }
+            }
+            None
+        }
+        Message::OpenNote => {
+            model.open_note();
+            None
+        }
+        Message::EditorClosed(request, opened) => {
+            // the note may have been written in the editor
+            model.note_preview = None;
+            match request {
+                EditorRequest::Note { uid, file, path } => {
+                    // a note the editor didn't save isn't linked to the task
+                    let has_note = model
+                        .task_position(uid)
+                        .is_some_and(|i| model.tasks[i].note().is_some());
+                    if opened && path.exists() && !has_note {
+                        model.modify_tasks("add note", vec![uid], |task| {
+                            task.add_word(&format!("note:{file}"))
+                        });
+                    }
+                }
+                EditorRequest::Task {
+                    uid,
+                    path,
+                    original,
+                } => {
+                    if let Some(text) = editor::take_edit(&path, &original, opened) {
+                        model.apply_task_edit(uid, &text)
+                    }
+                }
+                EditorRequest::File { path, original } => {
+                    if let Some(text) = editor::take_edit(&path, &original, opened) {
+                        model.apply_file_edit(&text)
+                    }
+                }
+            }
+            None
+        }
+        Message::ToggleDetail => {
+            model.detail = !model.detail;
+            None
+        }
+        Message::ToggleSidebar => {
+            model.sidebar.visible = !model.sidebar.visible;
+            model.focus = Focus::List;
+            None
+        }
+        Message::ToggleFocus => {
+            if model.focus == Focus::Sidebar {
+                model.focus = Focus::List;
+            } else {
+                model.sidebar.visible = true;
+                model.focus = Focus::Sidebar;
+            }
+            None
+        }
+        Message::SidebarKey(key_event) => {
+            match key_event.code {
+                KeyCode::Up | KeyCode::Char('k') => model.sidebar.list_state.select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => model.sidebar.list_state.select_next(),
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    let entries = model.tag_counts();
+                    let selected = model.sidebar.list_state.selected().unwrap_or(0);
+                    if let Some(entry) = entries.get(selected) {
+                        model.toggle_search_term(&entry.tag);
+                    }
+                }
+                KeyCode::Char('c') => {
+                    model.search.input = Input::default();
+                    model.filter_tasks();
+                }
+                _ => {}
+            }
+            None
+        }
+        Message::SetPriority(priority) => {
+            let uids = model.target_uids();
+            model.modify_tasks("set priority", uids, |task| task.set_priority(priority));
+            None
+        }
+        Message::RaisePriority => {
+            let uids = model.target_uids();
+            model.modify_tasks("raise priority", uids, Task::raise_priority);
+            None
+        }
+        Message::LowerPriority => {
+            let uids = model.target_uids();
+            model.modify_tasks("lower priority", uids, Task::lower_priority);
+            None
+        }
+        Message::ToggleMark => {
+            model.toggle_mark();
+            None
+        }
+        Message::MarkRange => {
+            model.toggle_mark_range();
+            None
+        }
+        Message::MarkAll => {
+            model.toggle_mark_all();
+            None
+        }
+        Message::ClearMarks => {
+            model.clear_marks();
+            None
+        }
+        Message::Archive => {
+            let uids = model.archive_targets();
+            if uids.is_empty() {
+                model.notices.warn("No done or marked tasks to archive");
+            } else {
+                model
+                    .notices
+                    .info(format!("Archived {}", count_tasks(uids.len())));
+            }
+            model.archive_tasks(uids);
+            None
+        }
+        Message::Undo => {
+            match model.undo() {
+                Ok(description) => model.notices.info(format!("Undid {description}")),
+                Err(e) => model.notices.warn(e),
+            }
+            None
+        }
+        Message::Redo => {
+            match model.redo() {
+                Ok(description) => model.notices.info(format!("Redid {description}")),
+                Err(e) => model.notices.warn(e),
+            }
             None
         }
     }
 }
 
 fn list_next_cyclic(list_state: &mut ListState, len: usize) {
+    if len == 0 {
+        return;
+    }
     if let Some(idx) = list_state.selected() {
         if len - 1 == idx {
             list_state.select_first()
@@ -799,9 +3078,58 @@ fn list_prev_cyclic(list_state: &mut ListState) {
     }
 }
 
+fn handle_mouse(model: &Model, mouse: MouseEvent) -> Option<Message> {
+    let (column, row) = (mouse.column, mouse.row);
+    let wheel = |down: bool| KeyEvent::from(if down { KeyCode::Down } else { KeyCode::Up });
+    match mouse.kind {
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+            let down = mouse.kind == MouseEventKind::ScrollDown;
+            match model.app_state {
+                AppState::Edit(_) | AppState::SearchInput if model.auto_complete.is_some() => {
+                    // the autocomplete moves back with shift like Shift-Tab
+                    let key = KeyEvent::new(
+                        KeyCode::Tab,
+                        if down {
+                            KeyModifiers::NONE
+                        } else {
+                            KeyModifiers::SHIFT
+                        },
+                    );
+                    Some(Message::AutoCompleteMove(key))
+                }
+                AppState::SavedSearches => Some(Message::HandleSavedSearchKeys(wheel(down))),
+                AppState::List if model.mouse_areas.in_list(column, row) => {
+                    Some(Message::Scroll(if down { 1 } else { -1 }))
+                }
+                _ => None,
+            }
+        }
+        MouseEventKind::Down(event::MouseButton::Left) => match model.app_state {
+            AppState::SavedSearches => model
+                .mouse_areas
+                .saved_search_at(column, row)
+                .and_then(|index| model.saved_searches.list.get(index))
+                .map(|search| Message::LoadSearch(search.clone())),
+            AppState::List => model
+                .mouse_areas
+                .task_at(column, row)
+                .map(|(index, target)| Message::ClickTask(index, target)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 fn handle_paste(model: &Model, text: String) -> Option<Message> {
     match model.app_state {
-        AppState::SearchInput => Some(Message::HandlePaste(text)),
+        AppState::Edit(_)
+        | AppState::SearchInput
+        | AppState::Goto(_)
+        | AppState::Tags(_)
+        | AppState::Palette(_) => Some(Message::HandlePaste(text)),
+        AppState::Capture(_) => Some(Message::CapturePaste(text)),
+        // pasting in the list captures a task for every line
+        AppState::List => Some(Message::OpenCapture(text)),
         _ => None,
     }
 }
@@ -839,3 +3167,191 @@ impl SavedSearches {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{update, AppState, InputState, Message, Model, Substitute};
+    use crate::config::Config;
+    use crate::keymap::Action;
+
+    fn lines(model: &Model) -> Vec<String> {
+        model.tasks.iter().map(|t| t.text.clone()).collect()
+    }
+
+    #[test]
+    fn archive_write_failure() {
+        let dir = std::env::temp_dir().join(format!("rtodo-archive-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let todo = dir.join("todo.txt");
+        fs::write(&todo, "x done\nopen").unwrap();
+        let config = Config {
+            file_path: todo.to_string_lossy().to_string(),
+            // a directory can't be appended to
+            archive_path: dir.to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let mut model = Model::new(vec!["x done", "open"], config, Vec::new());
+        let uids = model.archive_targets();
+        model.archive_tasks(uids);
+        assert!(model.write().is_err());
+        assert_eq!(fs::read_to_string(&todo).unwrap(), "x done\nopen");
+        assert_eq!(model.archived[0].text, "x done");
+        assert!(model.dirty);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn archive_undo() {
+        let dir = std::env::temp_dir().join(format!("rtodo-undo-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let todo = dir.join("todo.txt");
+        let done = dir.join("done.txt");
+        let config = Config {
+            file_path: todo.to_string_lossy().to_string(),
+            archive_path: done.to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let mut model = Model::new(vec!["x done", "open"], config, Vec::new());
+        let archive = |model: &mut Model| {
+            let uids = model.archive_targets();
+            model.archive_tasks(uids);
+        };
+
+        // an archive undone before the save isn't written
+        archive(&mut model);
+        update(&mut model, Message::Undo);
+        model.write().unwrap();
+        assert!(!done.exists());
+        assert_eq!(fs::read_to_string(&todo).unwrap(), "open\nx done");
+
+        // a redo archives the tasks again
+        update(&mut model, Message::Redo);
+        model.write().unwrap();
+        assert_eq!(fs::read_to_string(&done).unwrap(), "x done\n");
+        assert_eq!(fs::read_to_string(&todo).unwrap(), "open");
+
+        // a saved archive can't be undone, so nothing is appended twice
+        update(&mut model, Message::Undo);
+        assert_eq!(model.tasks.len(), 1);
+        update(&mut model, Message::Redo);
+        update(&mut model, Message::Undo);
+        model.write().unwrap();
+        assert_eq!(fs::read_to_string(&done).unwrap(), "x done\n");
+        assert_eq!(fs::read_to_string(&todo).unwrap(), "open");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn move_subtrees() {
+        let mut model = Model::new(
+            vec!["parent id:1", "child p:1", "other"],
+            Config::default(),
+            Vec::new(),
+        );
+        model.list_state.select(Some(0));
+        update(&mut model, Message::MoveTask(-1));
+        assert!(!model.dirty);
+        assert!(model.undo_stack.undo.is_empty());
+
+        update(&mut model, Message::MoveTask(1));
+        let visible: Vec<&str> = model
+            .visible_tasks()
+            .iter()
+            .map(|t| t.text.as_str())
+            .collect();
+        assert_eq!(visible, ["☐ other", "☐ parent id:1", "☐ child p:1"]);
+        assert_eq!(model.selected_task().unwrap().text, "☐ parent id:1");
+
+        // a subtask only moves among the subtasks of its parent
+        model.list_state.select(Some(2));
+        update(&mut model, Message::MoveTask(-1));
+        assert_eq!(model.undo_stack.undo.len(), 1);
+    }
+
+    #[test]
+    fn counted_motions() {
+        let tasks: Vec<String> = (0..20).map(|i| format!("task {i}")).collect();
+        let mut model = Model::new(
+            tasks.iter().map(String::as_str).collect(),
+            Config::default(),
+            Vec::new(),
+        );
+        model.list_state.select(Some(0));
+        let press = |model: &mut Model, count: usize, action: Action| {
+            model.pending.count = Some(count);
+            let mut msg = Some(Message::KeyAction(action));
+            while let Some(m) = msg {
+                msg = update(model, m);
+            }
+        };
+        press(&mut model, 5, Action::Next);
+        assert_eq!(model.list_state.selected(), Some(5));
+        press(&mut model, usize::MAX, Action::Next);
+        assert_eq!(model.list_state.selected(), Some(19));
+        press(&mut model, 3, Action::Prev);
+        assert_eq!(model.list_state.selected(), Some(16));
+        press(&mut model, 99_999_999, Action::Undo);
+        assert_eq!(model.list_state.selected(), Some(16));
+    }
+
+    #[test]
+    fn cancelled_counted_actions() {
+        let mut model = Model::new(vec!["a", "b", "c", "d"], Config::default(), Vec::new());
+        model.list_state.select(Some(0));
+        let run = |model: &mut Model, msg: Message| {
+            let mut msg = Some(msg);
+            while let Some(m) = msg {
+                msg = update(model, m);
+            }
+        };
+
+        // a cancelled counted action doesn't leave its tasks behind for the next one
+        model.pending.count = Some(3);
+        run(&mut model, Message::KeyAction(Action::Modify));
+        run(&mut model, Message::DiscardEditor);
+        assert!(model.marked.is_empty());
+        run(&mut model, Message::KeyAction(Action::Modify));
+        model.input = "+home".into();
+        run(&mut model, Message::InputAction(InputState::Modify));
+        assert_eq!(lines(&model), ["☐ a +home", "☐ b", "☐ c", "☐ d"]);
+        assert!(model.marked.is_empty());
+    }
+
+    #[test]
+    fn palette_without_matches() {
+        let mut model = Model::new(vec!["task"], Config::default(), Vec::new());
+        update(&mut model, Message::OpenPalette);
+        let mut press = |key: KeyEvent| update(&mut model, Message::PaletteKey(key));
+        for c in "zzzz".chars() {
+            press(KeyEvent::from(KeyCode::Char(c)));
+        }
+        press(KeyEvent::from(KeyCode::Down));
+        press(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL));
+        assert!(press(KeyEvent::from(KeyCode::Enter)).is_none());
+        assert!(matches!(model.app_state, AppState::List));
+    }
+
+    #[test]
+    fn empty_list_motions() {
+        let mut model = Model::new(Vec::new(), Config::default(), Vec::new());
+        // the selection is left over when a search matches nothing
+        model.list_state.select(Some(3));
+        update(&mut model, Message::Next);
+        update(&mut model, Message::Prev);
+        update(&mut model, Message::ToggleMark);
+        assert!(model.marked.is_empty());
+    }
+
+    #[test]
+    fn substitute_emptying_tasks() {
+        let mut model = Model::new(vec!["q", "qx foo"], Config::default(), Vec::new());
+        let changes = model.substitute_changes(&Substitute::parse("%s/q//").unwrap());
+        model.apply_substitute(&changes);
+        assert_eq!(lines(&model), ["x foo"]);
+        assert_eq!(model.first_done_index, 0);
+    }
+}
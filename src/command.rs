@@ -0,0 +1,314 @@
+use crate::tasks::SortKey;
+
+/// the commands typed after `:`, besides a line number and substitutes
+pub const COMMANDS: [&str; 10] = [
+    "archive", "filter", "move", "open", "q", "q!", "set", "sort", "w", "wq",
+];
+/// the commands which take an argument
+const ARGUMENT_COMMANDS: [&str; 5] = ["filter", "move", "open", "set", "sort"];
+/// the options `:set` can change, they are all true or false
+pub const SETTINGS: [&str; 6] = [
+    "add_creation_date",
+    "detail",
+    "move_done_to_end",
+    "prompt_complete_subtasks",
+    "sidebar",
+    "vim_mode",
+];
+
+/// A command typed after `:`, like `:42`, `:w` or `:sort due`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Goto(usize),
+    Write,
+    /// saves and quits, like `q` in the list
+    Quit,
+    QuitWithoutSave,
+    Sort(SortKey),
+    Archive,
+    /// searches for the text, an empty text clears the search
+    Filter(String),
+    Set(String, bool),
+    Open(String),
+    /// moves the selected task to the position in the list
+    Move(usize),
+    Substitute(Substitute),
+}
+
+impl Command {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        if let Ok(num) = input.parse::<usize>() {
+            return Ok(Command::Goto(num));
+        }
+        let (name, argument) = match input.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (input, ""),
+        };
+        let no_argument = |command| {
+            if argument.is_empty() {
+                Ok(command)
+            } else {
+                Err(format!(":{name} doesn't take an argument"))
+            }
+        };
+        match name {
+            "w" => no_argument(Command::Write),
+            "q" | "wq" | "x" => no_argument(Command::Quit),
+            "q!" => no_argument(Command::QuitWithoutSave),
+            "archive" => no_argument(Command::Archive),
+            "filter" => Ok(Command::Filter(argument.to_string())),
+            "sort" => SortKey::parse(argument).map(Command::Sort).ok_or_else(|| {
+                let keys: Vec<&str> = SortKey::ALL.iter().map(|k| k.name()).collect();
+                format!("Sort by one of {}", keys.join(", "))
+            }),
+            "set" => {
+                let (option, value) = match argument.split_once('=') {
+                    Some((option, value)) => (option.trim(), value.trim()),
+                    None => (argument, "true"),
+                };
+                if !SETTINGS.contains(&option) {
+                    return Err(format!("Unknown option: {option}"));
+                }
+                let value = value
+                    .parse()
+                    .map_err(|_| format!("{option} is true or false"))?;
+                Ok(Command::Set(option.to_string(), value))
+            }
+            "m" | "move" => argument
+                .parse()
+                .map(Command::Move)
+                .map_err(|_| format!(":{name} needs the number of a task")),
+            "open" if argument.is_empty() => Err(":open needs a file".to_string()),
+            "open" => Ok(Command::Open(argument.to_string())),
+            _ if is_substitute(input) => Substitute::parse(input).map(Command::Substitute),
+            _ => Err(format!("Unknown command: {name}")),
+        }
+    }
+}
+
+/// The inputs the input can be completed to, `arguments` gives the candidates
+/// for the argument of a command from its name and the argument typed so far
+pub fn complete(input: &str, arguments: impl Fn(&str, &str) -> Vec<String>) -> Vec<String> {
+    match input.split_once(' ') {
+        None => COMMANDS
+            .iter()
+            .filter(|c| c.starts_with(input))
+            .map(|c| {
+                if ARGUMENT_COMMANDS.contains(c) {
+                    format!("{c} ")
+                } else {
+                    c.to_string()
+                }
+            })
+            .collect(),
+        Some((name, argument)) => {
+            let argument = argument.trim_start();
+            let candidates = match name {
+                "sort" => SortKey::ALL.iter().map(|k| k.name().to_string()).collect(),
+                "set" => SETTINGS.iter().map(|s| format!("{s}=")).collect(),
+                _ => arguments(name, argument),
+            };
+            candidates
+                .into_iter()
+                .filter(|c| c.starts_with(argument))
+                .map(|c| format!("{name} {c}"))
+                .collect()
+        }
+    }
+}
+
+/// the longest start the completions share, which Tab completes to
+pub fn common_prefix(completions: &[String]) -> Option<String> {
+    let first = completions.first()?;
+    let mut prefix = first.as_str();
+    for completion in &completions[1..] {
+        let len = prefix
+            .char_indices()
+            .zip(completion.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8());
+        prefix = &prefix[..len];
+    }
+    Some(prefix.to_string())
+}
+
+/// A vim style `s/pattern/replacement/flags` command typed after `:`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitute {
+    pub pattern: String,
+    pub replacement: String,
+    /// replace every occurrence in a task instead of only the first one
+    pub global: bool,
+    /// `%s` applies to the whole file instead of the tasks in the list
+    pub whole_file: bool,
+}
+
+/// whether the input is `s` or `%s` followed by a delimiter, other words starting with `s` are commands
+fn is_substitute(input: &str) -> bool {
+    let input = input.strip_prefix('%').unwrap_or(input);
+    input
+        .strip_prefix('s')
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(is_delimiter)
+}
+
+fn is_delimiter(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace() && c != '\\'
+}
+
+impl Substitute {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let (whole_file, rest) = match input.strip_prefix('%') {
+            Some(rest) => (true, rest),
+            None => (false, input),
+        };
+        let rest = rest
+            .strip_prefix('s')
+            .ok_or_else(|| format!("Not a substitute command: {input}"))?;
+        let mut chars = rest.chars();
+        let delimiter = match chars.next() {
+            Some(d) if is_delimiter(d) => d,
+            _ => return Err("Substitute needs a delimiter like s/old/new/".to_string()),
+        };
+        let parts = split_escaped(chars.as_str(), delimiter);
+        if parts.len() > 3 {
+            return Err(format!("Too many '{delimiter}' in the substitute command"));
+        }
+        let pattern = parts[0].clone();
+        if pattern.is_empty() {
+            return Err("Substitute pattern is empty".to_string());
+        }
+        let replacement = parts.get(1).cloned().unwrap_or_default();
+        let mut global = false;
+        for flag in parts.get(2).map(|f| f.chars()).into_iter().flatten() {
+            match flag {
+                'g' => global = true,
+                _ => return Err(format!("Unknown substitute flag: {flag}")),
+            }
+        }
+
+        Ok(Self {
+            pattern,
+            replacement,
+            global,
+            whole_file,
+        })
+    }
+
+    /// the line with the pattern replaced, or None when the pattern isn't in the line
+    pub fn apply(&self, line: &str) -> Option<String> {
+        if !line.contains(&self.pattern) {
+            None
+        } else if self.global {
+            Some(line.replace(&self.pattern, &self.replacement))
+        } else {
+            Some(line.replacen(&self.pattern, &self.replacement, 1))
+        }
+    }
+}
+
+/// splits on the delimiter, a delimiter escaped with `\` is kept in the part
+fn split_escaped(input: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek() == Some(&delimiter) {
+            parts.last_mut().unwrap().push(delimiter);
+            chars.next();
+        } else if c == delimiter {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    // a trailing delimiter doesn't start a new part
+    if parts.len() > 1 && parts.last().is_some_and(|p| p.is_empty()) {
+        parts.pop();
+    }
+    parts
+}
+
+#[cfg(test)]
+mod test {
+    use super::{common_prefix, complete, Command, Substitute};
+    use crate::tasks::SortKey;
+
+    #[test]
+    fn substitute_parse() {
+        let sub = Substitute::parse("s/+oldproj/+newproj/").unwrap();
+        assert_eq!(sub.pattern, "+oldproj");
+        assert_eq!(sub.replacement, "+newproj");
+        assert!(!sub.global && !sub.whole_file);
+
+        let sub = Substitute::parse("%s#a\\#b#c#g").unwrap();
+        assert_eq!(sub.pattern, "a#b");
+        assert_eq!(sub.replacement, "c");
+        assert!(sub.global && sub.whole_file);
+
+        let sub = Substitute::parse("s/@home").unwrap();
+        assert_eq!(sub.replacement, "");
+
+        assert!(Substitute::parse("s//new/").is_err());
+        assert!(Substitute::parse("s/a/b/x").is_err());
+        assert!(Substitute::parse("sa").is_err());
+    }
+
+    #[test]
+    fn substitute_apply() {
+        let sub = Substitute::parse("s/a/b/").unwrap();
+        assert_eq!(sub.apply("a a"), Some("b a".to_string()));
+        assert_eq!(sub.apply("c"), None);
+        let sub = Substitute::parse("s/a/b/g").unwrap();
+        assert_eq!(sub.apply("a a"), Some("b b".to_string()));
+    }
+
+    #[test]
+    fn command_parse() {
+        assert_eq!(Command::parse("42"), Ok(Command::Goto(42)));
+        assert_eq!(Command::parse("wq"), Ok(Command::Quit));
+        assert_eq!(Command::parse("sort due"), Ok(Command::Sort(SortKey::Due)));
+        assert_eq!(
+            Command::parse("filter +work"),
+            Ok(Command::Filter("+work".to_string()))
+        );
+        assert_eq!(
+            Command::parse("set move_done_to_end=false"),
+            Ok(Command::Set("move_done_to_end".to_string(), false))
+        );
+        assert!(matches!(
+            Command::parse("s/a/b/"),
+            Ok(Command::Substitute(_))
+        ));
+        assert_eq!(Command::parse("m 3"), Ok(Command::Move(3)));
+        assert!(Command::parse("m").is_err());
+        assert!(Command::parse("sort size").is_err());
+        assert!(Command::parse("set colour=red").is_err());
+        assert!(Command::parse("w now").is_err());
+        assert!(Command::parse("frobnicate").is_err());
+        assert_eq!(
+            Command::parse("save"),
+            Err("Unknown command: save".to_string())
+        );
+        assert_eq!(Command::parse("sp"), Err("Unknown command: sp".to_string()));
+        assert!(matches!(
+            Command::parse("%s#a#b#"),
+            Ok(Command::Substitute(_))
+        ));
+    }
+
+    #[test]
+    fn command_complete() {
+        let files = |_: &str, _: &str| vec!["other.txt".to_string(), "done.txt".to_string()];
+        assert_eq!(complete("so", files), ["sort "]);
+        assert_eq!(complete("q", files), ["q", "q!"]);
+        assert_eq!(complete("sort d", files), ["sort due", "sort description"]);
+        assert_eq!(complete("open o", files), ["open other.txt"]);
+        assert_eq!(
+            common_prefix(&complete("sort d", files)),
+            Some("sort d".to_string())
+        );
+        assert_eq!(common_prefix(&[]), None);
+    }
+}
@@ -2,10 +2,14 @@ use directories::ProjectDirs;
 use ratatui::style::{palette::tailwind, Color};
 use serde::Deserialize;
 use std::{
+    collections::HashMap,
     env,
     fs::{self, read_to_string},
+    path::Path,
 };
 
+use crate::keymap::KeyList;
+
 #[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct Config {
@@ -13,7 +17,46 @@ pub struct Config {
     pub add_creation_date: bool,
     pub move_done_to_end: bool,
     pub searches_path: String,
+    pub archive_path: String,
+    /// the directory of the files `note:` keys point to
+    pub notes_dir: String,
+    /// the files the tasks and the searches entered before are kept in
+    pub task_history_path: String,
+    pub search_history_path: String,
+    pub sidebar: bool,
+    pub sidebar_width: u16,
+    pub prompt_complete_subtasks: bool,
+    pub detail: bool,
+    /// vim style normal and insert modes in the task editor and the search input
+    pub vim_mode: bool,
+    pub detail_position: DetailPosition,
+    /// size of the detail pane as a percentage of the list
+    pub detail_size: u16,
+    /// how tasks wider than the list are shown
+    pub overflow: Overflow,
+    /// select, edit and toggle tasks, tags and saved searches with the mouse
+    pub mouse: bool,
+    /// seconds after the last change the tasks are saved, 0 turns autosaving off
+    pub autosave: u64,
     pub theme: Theme,
+    /// keys of the actions which replace their default keys
+    pub keys: HashMap<String, KeyList>,
+}
+
+/// where the detail pane is placed next to the task list
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DetailPosition {
+    Right,
+    Bottom,
+}
+
+/// whether a long task is cut off at the edge of the list or continues on the next lines
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    Truncate,
+    Wrap,
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,6 +76,15 @@ pub struct Theme {
     pub text: Color,
     #[serde(with = "color_to_tui")]
     pub kv: Color,
+    #[serde(with = "color_to_tui")]
+    pub marked: Color,
+    /// backgrounds of the notices in the status line by their level
+    #[serde(with = "color_to_tui")]
+    pub info: Color,
+    #[serde(with = "color_to_tui")]
+    pub warn: Color,
+    #[serde(with = "color_to_tui")]
+    pub error: Color,
 }
 
 impl Default for Theme {
@@ -45,6 +97,10 @@ impl Default for Theme {
             project: tailwind::AMBER.c500,
             priority: tailwind::EMERALD.c500,
             kv: tailwind::PURPLE.c500,
+            marked: tailwind::INDIGO.c900,
+            info: tailwind::SKY.c800,
+            warn: tailwind::AMBER.c700,
+            error: tailwind::RED.c700,
         }
     }
 }
@@ -54,9 +110,24 @@ impl Default for Config {
         Self {
             file_path: "".to_string(),
             searches_path: "".to_string(),
+            archive_path: "".to_string(),
+            notes_dir: "".to_string(),
+            task_history_path: "".to_string(),
+            search_history_path: "".to_string(),
             move_done_to_end: true,
             add_creation_date: true,
+            sidebar: false,
+            sidebar_width: 30,
+            prompt_complete_subtasks: true,
+            detail: false,
+            vim_mode: false,
+            detail_position: DetailPosition::Right,
+            overflow: Overflow::Truncate,
+            detail_size: 40,
+            mouse: false,
+            autosave: 0,
             theme: Theme::default(),
+            keys: HashMap::new(),
         }
     }
 }
@@ -109,6 +180,29 @@ pub fn get_config() -> Config {
         config.file_path = file_name.to_string_lossy().to_string()
     }
 
+    if config.archive_path.is_empty() {
+        let mut path = Path::new(&config.file_path).to_path_buf();
+        path.set_file_name("done.txt");
+        config.archive_path = path.to_string_lossy().to_string()
+    }
+
+    if let Some(dirs) = ProjectDirs::from("", "ff2400t", "rtodo") {
+        let dir = dirs.config_dir();
+        if config.task_history_path.is_empty() {
+            config.task_history_path = dir.join("task_history.txt").to_string_lossy().to_string();
+        }
+        if config.search_history_path.is_empty() {
+            config.search_history_path =
+                dir.join("search_history.txt").to_string_lossy().to_string();
+        }
+    }
+
+    if config.notes_dir.is_empty() {
+        let mut path = Path::new(&config.file_path).to_path_buf();
+        path.set_file_name("notes");
+        config.notes_dir = path.to_string_lossy().to_string()
+    }
+
     config
 }
 
@@ -0,0 +1,176 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{self, Command},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A file to open in the external editor, the TUI is suspended until the editor exits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorRequest {
+    /// the note of the task with the uid, `file` is the value of its `note:` key
+    Note {
+        uid: u64,
+        file: String,
+        path: PathBuf,
+    },
+    /// the task with the uid written to a temporary file
+    Task {
+        uid: u64,
+        path: PathBuf,
+        original: String,
+    },
+    /// every task written to a temporary file
+    File { path: PathBuf, original: String },
+}
+
+impl EditorRequest {
+    pub fn path(&self) -> &Path {
+        match self {
+            EditorRequest::Note { path, .. }
+            | EditorRequest::Task { path, .. }
+            | EditorRequest::File { path, .. } => path,
+        }
+    }
+}
+
+/// the editor from `$VISUAL` or `$EDITOR`, falling back to vi
+fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// opens the file in the editor and waits for it to exit,
+/// the editor can have arguments like `code --wait`
+pub fn open(path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let editor = editor_command();
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = Command::new(program).args(parts).arg(path).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{program} exited with {status}")))
+    }
+}
+
+/// Writes the text to a new file in the temporary directory only the user can read.
+/// The file is created exclusively so an existing file or a symlink in its place is never written
+pub fn create_temp_file(kind: &str, text: &str) -> io::Result<PathBuf> {
+    let dir = env::temp_dir();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    for attempt in 0..100u32 {
+        let path = dir.join(format!(
+            "rtodo-{}-{nanos:08x}{attempt:02}-{kind}.txt",
+            process::id()
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(mut file) => {
+                if let Err(error) = file.write_all(text.as_bytes()) {
+                    let _ = fs::remove_file(&path);
+                    return Err(error);
+                }
+                return Ok(path);
+            }
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "no free name for a temporary file",
+    ))
+}
+
+/// the text of the temporary file after the editor exited, None when it wasn't changed.
+/// The temporary file is removed
+pub fn take_edit(path: &Path, original: &str, opened: bool) -> Option<String> {
+    let edited = if opened {
+        fs::read_to_string(path).ok()
+    } else {
+        None
+    };
+    let _ = fs::remove_file(path);
+    edited.filter(|text| text.trim_end() != original.trim_end())
+}
+
+/// a file name for a new note made from the words of the task, which doesn't exist in `dir` yet
+pub fn note_file_name(words: &[&str], dir: &Path) -> String {
+    let slug: Vec<String> = words
+        .iter()
+        .map(|w| {
+            w.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|w| !w.is_empty())
+        .take(5)
+        .collect();
+    let base = if slug.is_empty() {
+        "note".to_string()
+    } else {
+        slug.join("-")
+    };
+    let mut name = format!("{base}.md");
+    let mut count = 2;
+    while dir.join(&name).exists() {
+        name = format!("{base}-{count}.md");
+        count += 1;
+    }
+    name
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{create_temp_file, note_file_name, take_edit};
+
+    #[test]
+    fn note_names() {
+        let dir = Path::new("/nonexistent-rtodo-notes");
+        assert_eq!(
+            note_file_name(
+                &["Call", "Bob's", "office", "re:", "budget", "2024", "more"],
+                dir
+            ),
+            "call-bobs-office-re-budget.md"
+        );
+        assert_eq!(note_file_name(&["?!"], dir), "note.md");
+    }
+
+    #[test]
+    fn temp_files() {
+        let first = create_temp_file("task", "a task\n").unwrap();
+        let second = create_temp_file("task", "a task\n").unwrap();
+        assert_ne!(first, second);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_eq!(take_edit(&first, "a task", true), None);
+        assert!(!first.exists());
+        assert_eq!(
+            take_edit(&second, "b task", true).as_deref(),
+            Some("a task\n")
+        );
+        assert!(!second.exists());
+    }
+}
@@ -0,0 +1,617 @@
+use std::{collections::HashMap, fmt};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// An action of the task list which keys can be bound to in the `[keys]` table of the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Prev,
+    Next,
+    Quit,
+    QuitWithoutSave,
+    ToggleDone,
+    Delete,
+    Edit,
+    EditInEditor,
+    EditFile,
+    NewTask,
+    Capture,
+    CopyTask,
+    Subtask,
+    Save,
+    SaveSearch,
+    LoadSearch,
+    Search,
+    Undo,
+    Redo,
+    Report,
+    Help,
+    CommandLine,
+    RaisePriority,
+    LowerPriority,
+    ClearPriority,
+    Mark,
+    MarkRange,
+    MarkAll,
+    ClearMarks,
+    Modify,
+    Archive,
+    Tags,
+    ToggleSidebar,
+    ToggleDetail,
+    Note,
+    ToggleFocus,
+    ToggleCollapse,
+    Palette,
+    Top,
+    Bottom,
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    NextOverdue,
+    PrevOverdue,
+    SetMark,
+    JumpToMark,
+    MoveUp,
+    MoveDown,
+    MoveToTop,
+    MoveToBottom,
+}
+
+impl Action {
+    /// every action in the order the help lists them
+    pub const ALL: [Action; 52] = [
+        Action::Next,
+        Action::Prev,
+        Action::Top,
+        Action::Bottom,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::PageDown,
+        Action::PageUp,
+        Action::NextOverdue,
+        Action::PrevOverdue,
+        Action::SetMark,
+        Action::JumpToMark,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveToTop,
+        Action::MoveToBottom,
+        Action::ToggleDone,
+        Action::Delete,
+        Action::NewTask,
+        Action::Edit,
+        Action::CopyTask,
+        Action::Subtask,
+        Action::Capture,
+        Action::EditInEditor,
+        Action::EditFile,
+        Action::Note,
+        Action::Modify,
+        Action::Search,
+        Action::LoadSearch,
+        Action::SaveSearch,
+        Action::Save,
+        Action::Quit,
+        Action::QuitWithoutSave,
+        Action::Report,
+        Action::Help,
+        Action::CommandLine,
+        Action::RaisePriority,
+        Action::LowerPriority,
+        Action::ClearPriority,
+        Action::Mark,
+        Action::MarkRange,
+        Action::MarkAll,
+        Action::ClearMarks,
+        Action::Archive,
+        Action::Undo,
+        Action::Redo,
+        Action::Tags,
+        Action::ToggleSidebar,
+        Action::ToggleFocus,
+        Action::ToggleDetail,
+        Action::ToggleCollapse,
+        Action::Palette,
+    ];
+
+    /// the name of the action in the `[keys]` table
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Prev => "prev",
+            Action::Next => "next",
+            Action::Quit => "quit",
+            Action::QuitWithoutSave => "quit_without_save",
+            Action::ToggleDone => "toggle_done",
+            Action::Delete => "delete",
+            Action::Edit => "edit",
+            Action::EditInEditor => "edit_in_editor",
+            Action::EditFile => "edit_file",
+            Action::NewTask => "new_task",
+            Action::Capture => "capture",
+            Action::CopyTask => "copy_task",
+            Action::Subtask => "subtask",
+            Action::Save => "save",
+            Action::SaveSearch => "save_search",
+            Action::LoadSearch => "load_search",
+            Action::Search => "search",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Report => "report",
+            Action::Help => "help",
+            Action::CommandLine => "command_line",
+            Action::RaisePriority => "raise_priority",
+            Action::LowerPriority => "lower_priority",
+            Action::ClearPriority => "clear_priority",
+            Action::Mark => "mark",
+            Action::MarkRange => "mark_range",
+            Action::MarkAll => "mark_all",
+            Action::ClearMarks => "clear_marks",
+            Action::Modify => "modify",
+            Action::Archive => "archive",
+            Action::Tags => "tags",
+            Action::ToggleSidebar => "toggle_sidebar",
+            Action::ToggleDetail => "toggle_detail",
+            Action::Note => "note",
+            Action::ToggleFocus => "toggle_focus",
+            Action::ToggleCollapse => "toggle_collapse",
+            Action::Palette => "palette",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::HalfPageDown => "half_page_down",
+            Action::HalfPageUp => "half_page_up",
+            Action::PageDown => "page_down",
+            Action::PageUp => "page_up",
+            Action::NextOverdue => "next_overdue",
+            Action::PrevOverdue => "prev_overdue",
+            Action::SetMark => "set_mark",
+            Action::JumpToMark => "jump_to_mark",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveToTop => "move_to_top",
+            Action::MoveToBottom => "move_to_bottom",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Prev => "Move to prev task",
+            Action::Next => "Move to next task",
+            Action::Quit => "Quit",
+            Action::QuitWithoutSave => "Quit without saving any changes",
+            Action::ToggleDone => "Toggle Done for the Task",
+            Action::Delete => "Delete Task",
+            Action::Edit => "Edit the current task",
+            Action::EditInEditor => "Edit the task in $EDITOR",
+            Action::EditFile => "Edit the whole file in $EDITOR",
+            Action::NewTask => "Start writing a new task",
+            Action::Capture => "Capture several tasks at once, pasting in the list does the same",
+            Action::CopyTask => "Copy this task and open the editor modal",
+            Action::Subtask => "Add a subtask under the task",
+            Action::Save => "Save the current state to disk",
+            Action::SaveSearch => "Save a search to be reused later",
+            Action::LoadSearch => "Load a search",
+            Action::Search => "Start the search input",
+            Action::Undo => "Undo the last change",
+            Action::Redo => "Redo the last undone change",
+            Action::Report => "Open the report window",
+            Action::Help => "Help",
+            Action::CommandLine => {
+                "Command line, :42 goes to a task, :w, :q, :sort due, :filter +work and more"
+            }
+            Action::RaisePriority => "Raise the priority of the task",
+            Action::LowerPriority => "Lower the priority of the task",
+            Action::ClearPriority => "Clear the priority of the task",
+            Action::Mark => "Mark or unmark the task",
+            Action::MarkRange => "Start a range of marked tasks, press again to mark the range",
+            Action::MarkAll => "Mark all the tasks in the list, press again to unmark them",
+            Action::ClearMarks => "Clear the marked tasks",
+            Action::Modify => "Add or remove +project, @context and key:value words on the tasks",
+            Action::Archive => "Archive the done tasks or the marked tasks",
+            Action::Tags => {
+                "Manage projects and contexts, rename, merge or delete them in every task"
+            }
+            Action::ToggleSidebar => "Show or hide the sidebar with the projects and contexts",
+            Action::ToggleDetail => "Show or hide the details of the selected task",
+            Action::Note => "Write the note of the task in $EDITOR",
+            Action::ToggleFocus => "Move between the sidebar and the task list",
+            Action::ToggleCollapse => "Collapse or expand the subtasks of the task",
+            Action::Palette => "Open the command palette to find and run any action",
+            Action::Top => "Move to the first task",
+            Action::Bottom => "Move to the last task, or to the task of the count like 5G",
+            Action::HalfPageDown => "Move down half a page",
+            Action::HalfPageUp => "Move up half a page",
+            Action::PageDown => "Move down a page",
+            Action::PageUp => "Move up a page",
+            Action::NextOverdue => "Move to the next overdue task",
+            Action::PrevOverdue => "Move to the previous overdue task",
+            Action::SetMark => "Set a mark on the task with the letter typed next, like ma",
+            Action::JumpToMark => {
+                "Jump to the task with the mark of the letter typed next, like 'a"
+            }
+            Action::MoveUp => "Move the task up in the file",
+            Action::MoveDown => "Move the task down in the file",
+            Action::MoveToTop => "Move the task to the top of the file",
+            Action::MoveToBottom => "Move the task to the bottom of the file",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Prev => &["k", "Up"],
+            Action::Next => &["j", "Down"],
+            Action::Quit => &["q"],
+            Action::QuitWithoutSave => &["Q"],
+            Action::ToggleDone => &["d"],
+            Action::Delete => &["x"],
+            Action::Edit => &["e"],
+            Action::EditInEditor => &["Ctrl-e"],
+            Action::EditFile => &["Ctrl-o"],
+            Action::NewTask => &["n"],
+            Action::Capture => &["Ctrl-n"],
+            Action::CopyTask => &["c"],
+            Action::Subtask => &["o"],
+            Action::Save => &["s"],
+            Action::SaveSearch => &["a"],
+            Action::LoadSearch => &["l"],
+            Action::Search => &["/"],
+            Action::Undo => &["u"],
+            Action::Redo => &["Ctrl-r"],
+            Action::Report => &["r"],
+            Action::Help => &["~"],
+            Action::CommandLine => &[":"],
+            Action::RaisePriority => &["+"],
+            Action::LowerPriority => &["-"],
+            Action::ClearPriority => &["0"],
+            Action::Mark => &["Space"],
+            Action::MarkRange => &["V"],
+            Action::MarkAll => &["*"],
+            Action::ClearMarks => &["Esc"],
+            Action::Modify => &["t"],
+            Action::Archive => &["z"],
+            Action::Tags => &["p"],
+            Action::ToggleSidebar => &["b"],
+            Action::ToggleDetail => &["i"],
+            Action::Note => &["w"],
+            Action::ToggleFocus => &["Tab"],
+            Action::ToggleCollapse => &["Enter"],
+            Action::Palette => &["Ctrl-p"],
+            Action::Top => &["g g", "Home"],
+            Action::Bottom => &["G", "End"],
+            Action::HalfPageDown => &["Ctrl-d"],
+            Action::HalfPageUp => &["Ctrl-u"],
+            Action::PageDown => &["PageDown"],
+            Action::PageUp => &["PageUp"],
+            Action::NextOverdue => &["]"],
+            Action::PrevOverdue => &["["],
+            Action::SetMark => &["m"],
+            Action::JumpToMark => &["'"],
+            Action::MoveUp => &["Alt-k", "Alt-Up"],
+            Action::MoveDown => &["Alt-j", "Alt-Down"],
+            Action::MoveToTop => &["Alt-g"],
+            Action::MoveToBottom => &["Alt-G"],
+        }
+    }
+}
+
+/// A key with its modifiers, written like `j`, `Ctrl-r`, `Alt-Down` or `Space` in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        // a single char is always a key, so `-` isn't taken for a separator
+        while rest.chars().count() > 1 {
+            let Some((modifier, key)) = rest.split_once('-') else {
+                break;
+            };
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "a" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(format!("Unknown modifier '{modifier}' in '{text}'")),
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                key => match key.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("Unknown key '{rest}' in '{text}'")),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+
+    /// Shift is part of the char, `Q` is Shift-q, so it's left out for chars
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Up => write!(f, "🡩"),
+            KeyCode::Down => write!(f, "🡣"),
+            code => write!(f, "{code}"),
+        }
+    }
+}
+
+/// the keys of an action in the `[keys]` table, a single key or a list of them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    fn keys(&self) -> Vec<&str> {
+        match self {
+            KeyList::One(key) => vec![key.as_str()],
+            KeyList::Many(keys) => keys.iter().map(|k| k.as_str()).collect(),
+        }
+    }
+}
+
+/// Keys pressed one after the other, written like `g g` in the config
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(pub Vec<KeyChord>);
+
+impl KeySequence {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let chords = text
+            .split_whitespace()
+            .map(KeyChord::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if chords.is_empty() {
+            Err("A key can't be empty".to_string())
+        } else {
+            Ok(Self(chords))
+        }
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // plain chars are written together like `gg`
+        let plain = self.0.iter().all(|chord| {
+            chord.modifiers.is_empty() && matches!(chord.code, KeyCode::Char(c) if c != ' ')
+        });
+        let separator = if plain { "" } else { " " };
+        let names: Vec<String> = self.0.iter().map(|chord| chord.to_string()).collect();
+        write!(f, "{}", names.join(separator))
+    }
+}
+
+/// what the keys pressed so far are bound to
+#[derive(Debug, PartialEq, Eq)]
+pub enum Lookup {
+    Action(Action),
+    /// the keys start a longer sequence, more keys are needed
+    Prefix,
+    None,
+}
+
+/// The keys of every action, the keys set in the config replace the default keys of the action
+#[derive(Debug)]
+pub struct Keymap {
+    actions: HashMap<KeySequence, Action>,
+    keys: HashMap<Action, Vec<KeySequence>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&HashMap::new()).expect("the default keys don't conflict")
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap, the errors list unknown actions and keys,
+    /// and keys bound to more than one action
+    pub fn new(config: &HashMap<String, KeyList>) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+        for name in config.keys() {
+            if !Action::ALL.iter().any(|a| a.name() == name) {
+                errors.push(format!("Unknown action '{name}'"));
+            }
+        }
+
+        let mut actions: HashMap<KeySequence, Action> = HashMap::new();
+        let mut keys: HashMap<Action, Vec<KeySequence>> = HashMap::new();
+        for action in Action::ALL {
+            let names = match config.get(action.name()) {
+                Some(list) => list.keys(),
+                None => action.default_keys().to_vec(),
+            };
+            for name in names {
+                let sequence = match KeySequence::parse(name) {
+                    Ok(sequence) => sequence,
+                    Err(error) => {
+                        errors.push(error);
+                        continue;
+                    }
+                };
+                // a sequence starting with the keys of another action could never be finished
+                let other = actions.iter().find(|(keys, other)| {
+                    **other != action
+                        && (keys.0.starts_with(&sequence.0) || sequence.0.starts_with(&keys.0))
+                });
+                match other {
+                    Some((keys, other)) => errors.push(format!(
+                        "'{name}' of {} conflicts with '{keys}' of {}",
+                        action.name(),
+                        other.name()
+                    )),
+                    None => {
+                        actions.insert(sequence.clone(), action);
+                        keys.entry(action).or_default().push(sequence);
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Self { actions, keys })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// the action bound to the keys pressed so far
+    pub fn lookup(&self, keys: &[KeyChord]) -> Lookup {
+        if let Some(action) = self.actions.get(&KeySequence(keys.to_vec())) {
+            Lookup::Action(*action)
+        } else if self
+            .actions
+            .keys()
+            .any(|sequence| sequence.0.starts_with(keys))
+        {
+            Lookup::Prefix
+        } else {
+            Lookup::None
+        }
+    }
+
+    /// the action bound to the single key
+    pub fn action(&self, event: KeyEvent) -> Option<Action> {
+        match self.lookup(&[KeyChord::from(event)]) {
+            Lookup::Action(action) => Some(action),
+            _ => None,
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeySequence] {
+        self.keys.get(&action).map_or(&[], |k| k.as_slice())
+    }
+
+    /// the keys of the action for the help, like `j or 🡣`
+    pub fn key_names(&self, action: Action) -> String {
+        self.keys(action)
+            .iter()
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+            .join(" or ")
+    }
+
+    /// whether the key is bound to the action
+    pub fn is(&self, event: KeyEvent, action: Action) -> bool {
+        self.action(event) == Some(action)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{Action, KeyChord, KeyList, Keymap, Lookup};
+
+    #[test]
+    fn key_chords() {
+        let chord = KeyChord::parse("Ctrl-r").unwrap();
+        assert_eq!(chord.code, KeyCode::Char('r'));
+        assert_eq!(chord.modifiers, KeyModifiers::CONTROL);
+        assert_eq!(KeyChord::parse("-").unwrap().code, KeyCode::Char('-'));
+        assert_eq!(KeyChord::parse("Alt--").unwrap().code, KeyCode::Char('-'));
+        assert_eq!(KeyChord::parse("PageDown").unwrap().code, KeyCode::PageDown);
+        assert_eq!(
+            KeyChord::parse("Q").unwrap(),
+            KeyChord::from(KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT))
+        );
+        assert!(KeyChord::parse("Hyper-x").is_err());
+        assert!(KeyChord::parse("Nope").is_err());
+    }
+
+    #[test]
+    fn keymap_config() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(KeyEvent::from(KeyCode::Down)),
+            Some(Action::Next)
+        );
+        assert_eq!(keymap.key_names(Action::Next), "j or 🡣");
+
+        let config = HashMap::from([
+            ("next".to_string(), KeyList::One("n".to_string())),
+            ("new_task".to_string(), KeyList::Many(vec!["N".to_string()])),
+        ]);
+        let keymap = Keymap::new(&config).unwrap();
+        assert_eq!(
+            keymap.action(KeyEvent::from(KeyCode::Char('n'))),
+            Some(Action::Next)
+        );
+        assert_eq!(keymap.action(KeyEvent::from(KeyCode::Char('j'))), None);
+
+        let config = HashMap::from([
+            ("next".to_string(), KeyList::One("d".to_string())),
+            ("jump".to_string(), KeyList::One("g".to_string())),
+        ]);
+        let errors = Keymap::new(&config).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn keymap_sequences() {
+        let keymap = Keymap::default();
+        let g = KeyChord::parse("g").unwrap();
+        assert_eq!(keymap.lookup(&[g]), Lookup::Prefix);
+        assert_eq!(keymap.lookup(&[g, g]), Lookup::Action(Action::Top));
+        assert_eq!(keymap.key_names(Action::Top), "gg or Home");
+
+        // `g` alone would hide `g g`
+        let config = HashMap::from([("tags".to_string(), KeyList::One("g".to_string()))]);
+        assert!(Keymap::new(&config).is_err());
+    }
+}
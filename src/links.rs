@@ -0,0 +1,252 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::tasks::Task;
+
+/// A row of the task list when subtasks are shown under their parent
+#[derive(Debug, PartialEq, Eq)]
+pub struct TreeRow {
+    pub index: usize,
+    pub depth: usize,
+}
+
+/// Orders the tasks so that subtasks follow their parent, otherwise keeping their order.
+/// A task whose parent isn't in `tasks` is shown at the top level,
+/// and the subtasks of a collapsed parent are left out
+pub fn task_tree(tasks: &[&Task], collapsed: &HashSet<String>) -> Vec<TreeRow> {
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    for (index, task) in tasks.iter().enumerate() {
+        if let Some(id) = task.id() {
+            ids.entry(id).or_insert(index);
+        }
+    }
+
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); tasks.len()];
+    let mut roots = Vec::new();
+    for (index, task) in tasks.iter().enumerate() {
+        match task.parent().and_then(|p| ids.get(p)) {
+            Some(&parent) if parent != index => children[parent].push(index),
+            _ => roots.push(index),
+        }
+    }
+
+    let mut rows = Vec::with_capacity(tasks.len());
+    let mut visited = vec![false; tasks.len()];
+    // tasks whose parents form a cycle have no root, they start at the top level after the rest
+    for root in roots.into_iter().chain(0..tasks.len()) {
+        let mut stack = vec![(root, 0, false)];
+        while let Some((index, depth, hidden)) = stack.pop() {
+            if visited[index] {
+                continue;
+            }
+            visited[index] = true;
+            if !hidden {
+                rows.push(TreeRow { index, depth });
+            }
+            let hide_children =
+                hidden || tasks[index].id().is_some_and(|id| collapsed.contains(id));
+            for &child in children[index].iter().rev() {
+                stack.push((child, depth + 1, hide_children));
+            }
+        }
+    }
+    rows
+}
+
+/// the number of done subtasks and of all subtasks for the id of every parent
+pub fn subtask_progress(tasks: &[Task]) -> HashMap<String, (usize, usize)> {
+    let mut progress: HashMap<String, (usize, usize)> = HashMap::new();
+    for task in tasks {
+        if let Some(parent) = task.parent() {
+            let (done, total) = progress.entry(parent.to_string()).or_default();
+            *total += 1;
+            if task.done {
+                *done += 1
+            }
+        }
+    }
+    progress
+}
+
+/// uids of the tasks nested under the given tasks at any depth
+pub fn descendants(tasks: &[Task], uids: &[u64]) -> Vec<u64> {
+    let mut ids: Vec<&str> = tasks
+        .iter()
+        .filter(|t| uids.contains(&t.uid))
+        .filter_map(|t| t.id())
+        .collect();
+    let mut found: Vec<u64> = Vec::new();
+    while let Some(id) = ids.pop() {
+        for task in tasks.iter().filter(|t| t.parent() == Some(id)) {
+            if !found.contains(&task.uid) && !uids.contains(&task.uid) {
+                found.push(task.uid);
+                ids.extend(task.id());
+            }
+        }
+    }
+    found
+}
+
+/// uids of the open tasks with a dependency that isn't done yet,
+/// a dependency on an id no task has doesn't block
+pub fn blocked_tasks(tasks: &[Task]) -> HashSet<u64> {
+    let open_ids: HashSet<&str> = tasks
+        .iter()
+        .filter(|t| !t.done)
+        .filter_map(|t| t.id())
+        .collect();
+    tasks
+        .iter()
+        .filter(|t| !t.done && t.dependencies().any(|id| open_ids.contains(id)))
+        .map(|t| t.uid)
+        .collect()
+}
+
+/// uids of the open tasks that depend on any of the given tasks
+pub fn dependents(tasks: &[Task], uids: &[u64]) -> Vec<u64> {
+    let ids: Vec<&str> = tasks
+        .iter()
+        .filter(|t| uids.contains(&t.uid))
+        .filter_map(|t| t.id())
+        .collect();
+    tasks
+        .iter()
+        .filter(|t| !t.done && !uids.contains(&t.uid))
+        .filter(|t| t.dependencies().any(|id| ids.contains(&id)))
+        .map(|t| t.uid)
+        .collect()
+}
+
+/// the ids of every dependency cycle, each starting and ending with the same id
+pub fn dependency_cycles(tasks: &[Task]) -> Vec<Vec<String>> {
+    let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
+    for task in tasks {
+        if let Some(id) = task.id() {
+            graph.entry(id).or_default().extend(task.dependencies());
+        }
+    }
+    let mut ids: Vec<&str> = graph.keys().copied().collect();
+    ids.sort_unstable();
+
+    let mut cycles = Vec::new();
+    let mut finished: HashSet<&str> = HashSet::new();
+    for start in ids {
+        if finished.contains(start) {
+            continue;
+        }
+        // the path from `start` with the index of the next dependency to follow for each id
+        let mut path: Vec<(&str, usize)> = vec![(start, 0)];
+        while let Some((id, next)) = path.last_mut() {
+            let deps = graph.get(*id).map(Vec::as_slice).unwrap_or_default();
+            let Some(&dep) = deps.get(*next) else {
+                finished.insert(*id);
+                path.pop();
+                continue;
+            };
+            *next += 1;
+            if let Some(pos) = path.iter().position(|(p, _)| *p == dep) {
+                let mut cycle: Vec<String> =
+                    path[pos..].iter().map(|(p, _)| p.to_string()).collect();
+                cycle.push(dep.to_string());
+                cycles.push(cycle);
+            } else if graph.contains_key(dep) && !finished.contains(dep) {
+                path.push((dep, 0));
+            }
+        }
+    }
+    cycles
+}
+
+/// an id no task uses yet, one more than the largest numeric id
+pub fn next_id(tasks: &[Task]) -> String {
+    let max = tasks
+        .iter()
+        .filter_map(|t| t.id())
+        .filter_map(|id| id.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0);
+    (max + 1).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::{
+        blocked_tasks, dependency_cycles, dependents, descendants, next_id, task_tree, TreeRow,
+    };
+    use crate::tasks::Task;
+
+    #[test]
+    fn tree_order() {
+        let tasks: Vec<Task> = [
+            "child p:1",
+            "parent id:1",
+            "other",
+            "grandchild parent:2",
+            "second child id:2 p:1",
+        ]
+        .iter()
+        .map(|t| Task::new(t))
+        .collect();
+        let refs: Vec<&Task> = tasks.iter().collect();
+
+        let rows: Vec<(usize, usize)> = task_tree(&refs, &HashSet::new())
+            .into_iter()
+            .map(|TreeRow { index, depth }| (index, depth))
+            .collect();
+        assert_eq!(rows, vec![(1, 0), (0, 1), (4, 1), (3, 2), (2, 0)]);
+
+        let collapsed = HashSet::from(["1".to_string()]);
+        let rows: Vec<usize> = task_tree(&refs, &collapsed)
+            .into_iter()
+            .map(|row| row.index)
+            .collect();
+        assert_eq!(rows, vec![1, 2]);
+
+        let found = descendants(&tasks, &[tasks[1].uid]);
+        assert_eq!(found.len(), 3);
+        assert_eq!(next_id(&tasks), "3");
+    }
+
+    #[test]
+    fn tree_cycle() {
+        let tasks: Vec<Task> = ["a id:1 p:2", "b id:2 p:1"]
+            .iter()
+            .map(|t| Task::new(t))
+            .collect();
+        let refs: Vec<&Task> = tasks.iter().collect();
+        assert_eq!(task_tree(&refs, &HashSet::new()).len(), 2);
+    }
+
+    #[test]
+    fn dependencies() {
+        let tasks: Vec<Task> = [
+            "write id:1",
+            "review id:2 dep:1",
+            "x draft id:3",
+            "ship dep:2,3 dep:9",
+        ]
+        .iter()
+        .map(|t| Task::new(t))
+        .collect();
+        let blocked = blocked_tasks(&tasks);
+        assert!(blocked.contains(&tasks[1].uid) && blocked.contains(&tasks[3].uid));
+        assert_eq!(blocked.len(), 2);
+        assert_eq!(dependents(&tasks, &[tasks[0].uid]), vec![tasks[1].uid]);
+        assert!(dependency_cycles(&tasks).is_empty());
+
+        let tasks: Vec<Task> = [
+            "a id:1 dep:3",
+            "b id:2 dep:1",
+            "c id:3 dep:2",
+            "d id:4 dep:4",
+        ]
+        .iter()
+        .map(|t| Task::new(t))
+        .collect();
+        assert_eq!(
+            dependency_cycles(&tasks),
+            vec![vec!["1", "3", "2", "1"], vec!["4", "4"]]
+        );
+    }
+}
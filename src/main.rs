@@ -3,14 +3,31 @@ use std::fs::read_to_string;
 use app::{run_app, Model};
 
 mod app;
+mod command;
 mod config;
+mod editor;
 mod errors;
+mod keymap;
+mod links;
+mod mouse;
+mod notices;
+mod overflow;
+mod palette;
+mod readline;
 mod tasks;
 mod tui;
 mod ui;
+mod vim;
 
 fn main() -> color_eyre::Result<()> {
     let config = config::get_config();
+    if let Err(errors) = keymap::Keymap::new(&config.keys) {
+        println!("The keys in the config file have problems:");
+        for error in errors {
+            println!("  {error}");
+        }
+        return Ok(());
+    }
 
     let tasks_str = match read_to_string(config.file_path.as_str()) {
         Ok(str) => str,
@@ -30,15 +47,23 @@ fn main() -> color_eyre::Result<()> {
     };
     let mut model = Model::new(tasks, config, saved_searches);
     errors::install_hooks()?;
-    let mut terminal = tui::init()?;
-    let save_file = run_app(&mut terminal, &mut model)?;
+    let mut terminal = tui::init(model.config.mouse)?;
+    let ran = run_app(&mut terminal, &mut model);
 
-    tui::restore()?;
+    // the terminal is gone when it was closed, the tasks are saved even if it can't be restored
+    // or drawn to, the errors are returned once they are saved
+    let restored = tui::restore();
+    let save_file = match ran {
+        Ok(save_file) => save_file,
+        Err(_) => model.save_file,
+    };
 
     // this needs to matched after restoring the terminal checked so that the line is printed to the console
     if save_file && model.write().is_err() {
         println!("There was an error in saving the todo.txt file")
     }
 
+    ran?;
+    restored?;
     Ok(())
 }
@@ -0,0 +1,121 @@
+use std::ops::Range;
+
+use ratatui::layout::{Position, Rect};
+
+/// the columns of the clickable parts of a row of the task list
+#[derive(Debug, Default, Clone)]
+pub struct RowArea {
+    pub checkbox: Range<u16>,
+    /// the `+project` and `@context` words with their columns
+    pub tags: Vec<(Range<u16>, String)>,
+    /// the rows a wrapped task takes, 0 counts as 1
+    pub lines: u16,
+}
+
+/// what part of a task was clicked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClickTarget {
+    Task,
+    Checkbox,
+    Tag(String),
+}
+
+/// Where the clickable widgets were drawn last, mouse events are matched against them
+#[derive(Debug, Default, Clone)]
+pub struct MouseAreas {
+    /// the rows of the task list without its borders
+    pub list: Rect,
+    /// the index of the task in the first row of the list
+    pub list_offset: usize,
+    /// every task of the list, not just the ones in view
+    pub rows: Vec<RowArea>,
+    /// the rows of the saved searches popup without its borders
+    pub saved_searches: Rect,
+    pub saved_searches_offset: usize,
+}
+
+impl MouseAreas {
+    /// the index in the list of the task at the position and the part of it there
+    pub fn task_at(&self, column: u16, row: u16) -> Option<(usize, ClickTarget)> {
+        if !self.in_list(column, row) {
+            return None;
+        }
+        let mut top = self.list.y;
+        for (index, area) in self.rows.iter().enumerate().skip(self.list_offset) {
+            let bottom = top + area.lines.max(1);
+            if row >= bottom {
+                top = bottom;
+                continue;
+            }
+            // the checkbox and the tags are found on the first line of a wrapped task only
+            let target = if row != top {
+                ClickTarget::Task
+            } else if area.checkbox.contains(&column) {
+                ClickTarget::Checkbox
+            } else if let Some((_, tag)) = area.tags.iter().find(|(cols, _)| cols.contains(&column))
+            {
+                ClickTarget::Tag(tag.clone())
+            } else {
+                ClickTarget::Task
+            };
+            return Some((index, target));
+        }
+        None
+    }
+
+    /// the index of the saved search at the position
+    pub fn saved_search_at(&self, column: u16, row: u16) -> Option<usize> {
+        row_index(self.saved_searches, self.saved_searches_offset, column, row)
+    }
+
+    pub fn in_list(&self, column: u16, row: u16) -> bool {
+        self.list.contains(Position::new(column, row))
+    }
+}
+
+fn row_index(area: Rect, offset: usize, column: u16, row: u16) -> Option<usize> {
+    if area.contains(Position::new(column, row)) {
+        Some(offset + (row - area.y) as usize)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::layout::Rect;
+
+    use super::{ClickTarget, MouseAreas, RowArea};
+
+    #[test]
+    fn click_targets() {
+        let areas = MouseAreas {
+            list: Rect::new(5, 2, 40, 10),
+            list_offset: 3,
+            rows: vec![
+                RowArea {
+                    checkbox: 5..7,
+                    tags: vec![(12..17, "+work".to_string())],
+                    lines: 1,
+                };
+                5
+            ],
+            ..Default::default()
+        };
+        assert_eq!(areas.task_at(5, 2), Some((3, ClickTarget::Checkbox)));
+        assert_eq!(
+            areas.task_at(14, 3),
+            Some((4, ClickTarget::Tag("+work".to_string())))
+        );
+        assert_eq!(areas.task_at(9, 2), Some((3, ClickTarget::Task)));
+        assert_eq!(areas.task_at(9, 4), None);
+        assert_eq!(areas.task_at(1, 2), None);
+
+        let mut wrapped = areas.clone();
+        wrapped.rows[3].lines = 3;
+        assert_eq!(wrapped.task_at(5, 2), Some((3, ClickTarget::Checkbox)));
+        assert_eq!(wrapped.task_at(5, 4), Some((3, ClickTarget::Task)));
+        assert_eq!(wrapped.task_at(5, 5), Some((4, ClickTarget::Checkbox)));
+        assert_eq!(wrapped.task_at(5, 6), None);
+    }
+}
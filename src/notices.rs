@@ -0,0 +1,117 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// how many notices are kept, the oldest are dropped first
+const NOTICE_LIMIT: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// how long a notice of the level is shown, errors stay the longest
+    fn duration(self) -> Duration {
+        match self {
+            Level::Info => Duration::from_secs(3),
+            Level::Warn => Duration::from_secs(5),
+            Level::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+/// A message shown in the status line until it expires
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub level: Level,
+    pub text: String,
+    pub expires: Instant,
+}
+
+/// The notices waiting to expire, the newest is shown
+#[derive(Debug, Default)]
+pub struct Notices {
+    queue: VecDeque<Notice>,
+}
+
+impl Notices {
+    pub fn push(&mut self, level: Level, text: impl Into<String>) {
+        self.push_at(level, text, Instant::now());
+    }
+
+    fn push_at(&mut self, level: Level, text: impl Into<String>, now: Instant) {
+        if self.queue.len() == NOTICE_LIMIT {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(Notice {
+            level,
+            text: text.into(),
+            expires: now + level.duration(),
+        });
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(Level::Info, text);
+    }
+
+    pub fn warn(&mut self, text: impl Into<String>) {
+        self.push(Level::Warn, text);
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(Level::Error, text);
+    }
+
+    /// drops the expired notices, returns whether any were dropped
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let len = self.queue.len();
+        self.queue.retain(|n| n.expires > now);
+        self.queue.len() != len
+    }
+
+    /// the notice shown in the status line
+    pub fn current(&self) -> Option<&Notice> {
+        self.queue.back()
+    }
+
+    /// how long until the next notice expires
+    pub fn next_expiry(&self, now: Instant) -> Option<Duration> {
+        self.queue
+            .iter()
+            .map(|n| n.expires.saturating_duration_since(now))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{Level, Notices, NOTICE_LIMIT};
+
+    #[test]
+    fn notices_expire() {
+        let now = Instant::now();
+        let mut notices = Notices::default();
+        notices.push_at(Level::Error, "Failed to save", now);
+        notices.push_at(Level::Info, "Saved", now);
+        assert_eq!(notices.current().unwrap().text, "Saved");
+        assert_eq!(notices.next_expiry(now), Some(Duration::from_secs(3)));
+
+        assert!(notices.expire(now + Duration::from_secs(4)));
+        assert_eq!(notices.current().unwrap().level, Level::Error);
+        assert!(!notices.expire(now + Duration::from_secs(5)));
+        assert!(notices.expire(now + Duration::from_secs(8)));
+        assert!(notices.current().is_none());
+
+        for i in 0..NOTICE_LIMIT + 2 {
+            notices.push_at(Level::Info, i.to_string(), now);
+        }
+        assert_eq!(notices.queue.len(), NOTICE_LIMIT);
+        assert_eq!(notices.queue.front().unwrap().text, "2");
+    }
+}
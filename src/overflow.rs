@@ -0,0 +1,135 @@
+use ratatui::text::{Line, Span};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const ELLIPSIS: &str = "…";
+
+/// Cuts the spans at the width and ends them with an ellipsis when they don't fit
+pub fn truncate<'a>(spans: Vec<Span<'a>>, width: usize) -> Vec<Span<'a>> {
+    let total: usize = spans.iter().map(Span::width).sum();
+    if total <= width {
+        return spans;
+    }
+    let mut room = width.saturating_sub(ELLIPSIS.width());
+    let mut cut = Vec::new();
+    for span in spans {
+        let span_width = span.width();
+        if span_width <= room {
+            room -= span_width;
+            cut.push(span);
+            continue;
+        }
+        let (head, _) = split_at_width(&span.content, room);
+        cut.push(Span::styled(head, span.style));
+        break;
+    }
+    if width > 0 {
+        cut.push(Span::raw(ELLIPSIS));
+    }
+    cut
+}
+
+/// Breaks the spans into lines of the width between words, words longer than a line are split.
+/// The lines after the first start with `indent` spaces
+pub fn wrap(spans: &[Span], width: usize, indent: usize) -> Vec<Line<'static>> {
+    let width = width.max(1);
+    let indent = indent.min(width / 2);
+    let mut lines = Vec::new();
+    let mut line: Vec<Span<'static>> = Vec::new();
+    let mut used = 0;
+    // the width at the start of the current line, nothing fits when a word is only after it
+    let mut start = 0;
+    for span in spans {
+        for word in words(&span.content) {
+            let mut word = word.to_string();
+            if used + word.width() > width && used > start {
+                lines.push(Line::from(std::mem::take(&mut line)));
+                line.push(Span::raw(" ".repeat(indent)));
+                used = indent;
+                start = indent;
+                word = word.trim_start().to_string();
+            }
+            while used + word.width() > width {
+                let (mut head, mut tail) = split_at_width(&word, width - used);
+                if head.is_empty() {
+                    // a wide char on a line too narrow for it is shown anyway
+                    let mut chars = word.chars();
+                    head = chars.next().map(String::from).unwrap_or_default();
+                    tail = chars.collect();
+                    if tail.is_empty() {
+                        word = head;
+                        break;
+                    }
+                }
+                line.push(Span::styled(head, span.style));
+                lines.push(Line::from(std::mem::take(&mut line)));
+                line.push(Span::raw(" ".repeat(indent)));
+                used = indent;
+                start = indent;
+                word = tail;
+            }
+            used += word.width();
+            line.push(Span::styled(word, span.style));
+        }
+    }
+    lines.push(Line::from(line));
+    lines
+}
+
+/// the words of the text, each with the spaces before it
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut begin = 0;
+    let mut previous_space = true;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() && !previous_space {
+            words.push(&text[begin..i]);
+            begin = i;
+        }
+        previous_space = c.is_whitespace();
+    }
+    if begin < text.len() {
+        words.push(&text[begin..]);
+    }
+    words
+}
+
+/// the start of the text which fits in the width and the rest
+fn split_at_width(text: &str, width: usize) -> (String, String) {
+    let mut used = 0;
+    for (i, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            return (text[..i].to_string(), text[i..].to_string());
+        }
+    }
+    (text.to_string(), String::new())
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::text::{Line, Span};
+
+    use super::{truncate, wrap};
+
+    fn text(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn overflow_lines() {
+        let spans = vec![Span::raw("☐ "), Span::raw("buy milk"), Span::raw(" +home")];
+        let line = |spans: Vec<Span>| Line::from(spans).to_string();
+        assert_eq!(line(truncate(spans.clone(), 20)), "☐ buy milk +home");
+        assert_eq!(line(truncate(spans.clone(), 9)), "☐ buy mi…");
+        assert_eq!(line(truncate(spans.clone(), 10)), "☐ buy mil…");
+        assert_eq!(line(truncate(spans.clone(), 0)), "");
+
+        assert_eq!(text(&wrap(&spans, 20, 2)), ["☐ buy milk +home"]);
+        assert_eq!(text(&wrap(&spans, 10, 2)), ["☐ buy milk", "  +home"]);
+        assert_eq!(
+            text(&wrap(&spans, 6, 2)),
+            ["☐ buy", "  milk", "  +hom", "  e"]
+        );
+        assert_eq!(text(&wrap(&[Span::raw("日本")], 1, 0)), ["日", "本"]);
+    }
+}
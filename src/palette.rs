@@ -0,0 +1,85 @@
+use ratatui::widgets::ListState;
+use tui_input::Input;
+
+use crate::app::Message;
+
+/// An entry of the command palette, the message is sent when it's chosen
+#[derive(Debug, Clone)]
+pub struct PaletteEntry {
+    pub label: String,
+    /// the keys bound to the entry, empty when it has none
+    pub keys: String,
+    pub message: Message,
+}
+
+/// Every action with the entries typed in the input fuzzy matched against it
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub input: Input,
+    pub list_state: ListState,
+    pub entries: Vec<PaletteEntry>,
+}
+
+impl Palette {
+    pub fn new(entries: Vec<PaletteEntry>) -> Self {
+        Self {
+            input: Input::default(),
+            list_state: ListState::default().with_selected(Some(0)),
+            entries,
+        }
+    }
+
+    /// the entries matching the input, the closest matches first
+    pub fn matches(&self) -> Vec<&PaletteEntry> {
+        let query = self.input.value();
+        let mut matches: Vec<(usize, &PaletteEntry)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| fuzzy_score(query, &entry.label).map(|score| (score, entry)))
+            .collect();
+        // the sort is stable so equal matches keep the order of the entries
+        matches.sort_by_key(|(score, _)| *score);
+        matches.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    pub fn selected(&self) -> Option<&PaletteEntry> {
+        self.matches()
+            .get(self.list_state.selected().unwrap_or(0))
+            .copied()
+    }
+}
+
+/// Matches the chars of the query in order anywhere in the text, ignoring case.
+/// Lower scores are closer matches, chars far apart and a late start cost more
+pub fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut first = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|t| *t == c)?;
+        if first.is_some() {
+            score += found - position;
+        }
+        first.get_or_insert(found);
+        position = found + 1;
+    }
+    Some(score * 2 + first.unwrap_or(0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::fuzzy_score;
+
+    #[test]
+    fn fuzzy_scores() {
+        assert_eq!(fuzzy_score("", "Archive"), Some(0));
+        assert_eq!(fuzzy_score("arc", "Archive"), Some(0));
+        assert!(fuzzy_score("xyz", "Archive").is_none());
+        assert!(fuzzy_score("srt", "Sort by due").is_some());
+        assert!(
+            fuzzy_score("sd", "Sort by due").unwrap()
+                < fuzzy_score("sd", "Save the current state to disk").unwrap()
+        );
+    }
+}
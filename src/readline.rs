@@ -0,0 +1,209 @@
+use std::{
+    fs::{create_dir_all, read_to_string, write},
+    io,
+    path::Path,
+};
+
+use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use tui_input::{backend::crossterm::EventHandler, Input, InputRequest};
+
+const HISTORY_LIMIT: usize = 500;
+
+/// Handles the key with emacs style bindings on top of the `tui_input` defaults.
+/// Alt-b/f move by words, Ctrl-w, Alt-d, Ctrl-k and Ctrl-u kill text into the kill buffer
+/// and Ctrl-y yanks it back
+pub fn handle_key(input: &mut Input, key: KeyEvent, kill_buffer: &mut String) {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key
+        .modifiers
+        .intersects(KeyModifiers::ALT | KeyModifiers::META);
+    let kill = match key.code {
+        KeyCode::Char('b') if alt => {
+            input.handle(InputRequest::GoToPrevWord);
+            return;
+        }
+        KeyCode::Char('f') if alt => {
+            input.handle(InputRequest::GoToNextWord);
+            return;
+        }
+        KeyCode::Char('y') if ctrl => {
+            for c in kill_buffer.chars() {
+                input.handle(InputRequest::InsertChar(c));
+            }
+            return;
+        }
+        KeyCode::Char('u') if ctrl => {
+            // readline kills to the start of the line, `tui_input` clears the whole line
+            kill_before(input, 0, kill_buffer);
+            return;
+        }
+        KeyCode::Char('w') if ctrl => {
+            // words end at whitespace so that `+project` is killed as a whole
+            let before: Vec<char> = input.value().chars().take(input.cursor()).collect();
+            let end = before.iter().rposition(|c| !c.is_whitespace()).unwrap_or(0);
+            let start = before[..end]
+                .iter()
+                .rposition(|c| c.is_whitespace())
+                .map_or(0, |i| i + 1);
+            kill_before(input, start, kill_buffer);
+            return;
+        }
+        KeyCode::Char('d') if alt => InputRequest::DeleteNextWord,
+        KeyCode::Backspace if alt => InputRequest::DeletePrevWord,
+        KeyCode::Char('k') if ctrl => InputRequest::DeleteTillEnd,
+        _ => {
+            input.handle_event(&Event::Key(key));
+            return;
+        }
+    };
+    let before = input.value().to_string();
+    input.handle(kill);
+    let killed = removed_text(&before, input.value());
+    if !killed.is_empty() {
+        *kill_buffer = killed;
+    }
+}
+
+/// kills the text from the char at `start` to the cursor
+fn kill_before(input: &mut Input, start: usize, kill_buffer: &mut String) {
+    let cursor = input.cursor();
+    let chars: Vec<char> = input.value().chars().collect();
+    let killed: String = chars[start..cursor].iter().collect();
+    let value: String = chars[..start].iter().chain(&chars[cursor..]).collect();
+    *input = Input::new(value).with_cursor(start);
+    if !killed.is_empty() {
+        *kill_buffer = killed;
+    }
+}
+
+/// the text removed from `before` to get `after`, which is a single run of chars
+fn removed_text(before: &str, after: &str) -> String {
+    let prefix = before
+        .chars()
+        .zip(after.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let removed = before.chars().count().saturating_sub(after.chars().count());
+    before.chars().skip(prefix).take(removed).collect()
+}
+
+/// Entries entered in an input, browsed with Up and Down and kept in a file between sessions
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// the entry being shown while browsing, None when back at the text being written
+    position: Option<usize>,
+    /// the text being written before browsing started
+    draft: String,
+    /// the file the entries are kept in, they aren't kept when it's empty
+    path: String,
+}
+
+impl History {
+    pub fn load(path: &str) -> Self {
+        let entries = if path.is_empty() {
+            Vec::new()
+        } else {
+            read_to_string(path)
+                .map(|s| s.lines().map(|l| l.to_string()).collect())
+                .unwrap_or_default()
+        };
+        Self {
+            entries,
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// adds the entry as the newest one, moving it there if it was entered before
+    pub fn push(&mut self, entry: &str) -> io::Result<()> {
+        self.reset();
+        let entry = entry.trim();
+        if entry.is_empty() {
+            return Ok(());
+        }
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > HISTORY_LIMIT {
+            self.entries.drain(..self.entries.len() - HISTORY_LIMIT);
+        }
+        if self.path.is_empty() {
+            return Ok(());
+        }
+        if let Some(dir) = Path::new(&self.path).parent() {
+            create_dir_all(dir)?;
+        }
+        write(&self.path, self.entries.join("\n"))
+    }
+
+    /// the entry before the one shown, `current` is kept to come back to
+    pub fn prev(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None => {
+                self.draft = current.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+            Some(position) => position.checked_sub(1)?,
+        };
+        self.position = Some(position);
+        self.entries.get(position).map(|e| e.as_str())
+    }
+
+    /// the entry after the one shown, or the text written before browsing after the newest one
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            self.entries.get(position + 1).map(|e| e.as_str())
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use tui_input::Input;
+
+    use super::{handle_key, History};
+
+    #[test]
+    fn kill_and_yank() {
+        let mut input = Input::new("buy milk +home".to_string());
+        let mut kill_buffer = String::new();
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        let alt = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT);
+
+        handle_key(&mut input, ctrl('w'), &mut kill_buffer);
+        assert_eq!(input.value(), "buy milk ");
+        assert_eq!(kill_buffer, "+home");
+        handle_key(&mut input, ctrl('a'), &mut kill_buffer);
+        handle_key(&mut input, ctrl('y'), &mut kill_buffer);
+        assert_eq!(input.value(), "+homebuy milk ");
+        handle_key(&mut input, alt('f'), &mut kill_buffer);
+        handle_key(&mut input, ctrl('u'), &mut kill_buffer);
+        assert_eq!(kill_buffer, "+homebuy ");
+        assert_eq!(input.value(), "milk ");
+    }
+
+    #[test]
+    fn history() {
+        let mut history = History::default();
+        history.push("one").unwrap();
+        history.push("two").unwrap();
+        history.push("one").unwrap();
+        assert_eq!(history.prev("draft"), Some("one"));
+        assert_eq!(history.prev("one"), Some("two"));
+        assert_eq!(history.prev("two"), None);
+        assert_eq!(history.next(), Some("one"));
+        assert_eq!(history.next(), Some("draft"));
+        assert_eq!(history.next(), None);
+    }
+}
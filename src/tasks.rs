@@ -1,17 +1,33 @@
 use chrono::{format::StrftimeItems, Days, Local, Months, NaiveDate};
+use std::{
+    path::{Component, Path},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 const DONE_PREFIX: &str = "x ";
 const PENDING_PREFIX: &str = "☐ ";
 const DUE_KEY: &str = "due:";
 const REC_KEY: &str = "rec:";
+const PRIORITY_KEY: &str = "Pri:";
+const ID_KEY: &str = "id:";
+const PARENT_KEYS: [&str; 2] = ["parent:", "p:"];
+const DEPENDENCY_KEY: &str = "dep:";
+const NOTE_KEY: &str = "note:";
 pub const DATE_FORMAT_STR: &str = "%Y-%m-%d";
 pub const DATE_FORMAT_CONST: StrftimeItems<'_> = StrftimeItems::new(DATE_FORMAT_STR);
 
+/// separators of nested projects like `+work.release.v2` or `+work/release/v2`
+pub(crate) const PROJECT_SEPARATORS: [char; 2] = ['.', '/'];
+
+static NEXT_UID: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Clone, Debug)]
 pub struct Task {
     pub text: String,
     pub arr: Vec<TaskSection>,
     pub done: bool,
+    /// identifies the task for the current session, it isn't written to the file
+    pub uid: u64,
 }
 
 #[derive(Clone, Debug)]
@@ -38,11 +54,16 @@ impl Task {
         };
 
         let arr = parse_task(&text);
-        Self { done, text, arr }
+        let uid = NEXT_UID.fetch_add(1, Ordering::Relaxed);
+        Self {
+            done,
+            text,
+            arr,
+            uid,
+        }
     }
 
     pub fn toggle_done(&mut self) -> Option<String> {
-        const PRIORITY_KEY: &str = "Pri:";
         if self.done {
             self.done = false;
             let text = self.text.clone();
@@ -103,6 +124,407 @@ impl Task {
         };
         None
     }
+
+    /// priority of the task, for a done task this is read from the `Pri:` key
+    pub fn priority(&self) -> Option<char> {
+        if self.done {
+            self.text
+                .split_whitespace()
+                .skip(1)
+                .find_map(|word| word.strip_prefix(PRIORITY_KEY))
+                .and_then(|pri| pri.chars().next())
+        } else {
+            let rest = self.text.strip_prefix(PENDING_PREFIX).unwrap_or(&self.text);
+            get_priority(rest).1.chars().next()
+        }
+    }
+
+    /// Sets or clears the priority of the task.
+    /// A pending task gets the priority at the start as per the todo.txt spec,
+    /// while a done task stores it in the `Pri:` key so that `toggle_done` can restore it
+    pub fn set_priority(&mut self, priority: Option<char>) {
+        let priority = priority
+            .map(|pri| pri.to_ascii_uppercase())
+            .filter(|pri| pri.is_ascii_uppercase());
+        if self.done {
+            let rest = self
+                .text
+                .split_whitespace()
+                .filter(|word| !word.starts_with(PRIORITY_KEY))
+                .collect::<Vec<&str>>()
+                .join(" ");
+            self.text = match priority {
+                Some(pri) => format!("{rest} {PRIORITY_KEY}{pri}"),
+                None => rest,
+            };
+        } else {
+            let rest = self.text.strip_prefix(PENDING_PREFIX).unwrap_or(&self.text);
+            let (rest, _) = get_priority(rest);
+            let rest = rest.trim_start();
+            self.text = match priority {
+                Some(pri) => format!("{PENDING_PREFIX}({pri}) {rest}"),
+                None => format!("{PENDING_PREFIX}{rest}"),
+            };
+        }
+        self.arr = parse_task(&self.text);
+    }
+
+    fn set_words(&mut self, words: &[&str]) {
+        let body = words.join(" ");
+        self.text = if self.done {
+            body
+        } else {
+            format!("{PENDING_PREFIX}{body}")
+        };
+        self.arr = parse_task(&self.text);
+    }
+
+    /// Adds the word to the end of the task unless it is already present.
+    /// A `key:value` word replaces the value of the key if the task already has it
+    pub fn add_word(&mut self, word: &str) {
+        let text = self.text.clone();
+        let mut words = words(&text);
+        match kv_key(word) {
+            Some(key) => {
+                let mut replaced = false;
+                words.retain_mut(|w| {
+                    if kv_key(w) == Some(key) {
+                        if replaced {
+                            return false;
+                        }
+                        *w = word;
+                        replaced = true;
+                    }
+                    true
+                });
+                if !replaced {
+                    words.push(word)
+                }
+            }
+            None => {
+                if !words.contains(&word) {
+                    words.push(word)
+                }
+            }
+        };
+        self.set_words(&words);
+    }
+
+    /// Removes every occurrence of the word, a bare `key:` removes the key whatever its value
+    pub fn remove_word(&mut self, word: &str) {
+        let text = self.text.clone();
+        let mut words = words(&text);
+        let done = self.done;
+        let mut index = 0;
+        words.retain(|w| {
+            index += 1;
+            // the `x` of a done task is never removed
+            if done && index == 1 {
+                return true;
+            }
+            match word.strip_suffix(':') {
+                Some(key) if kv_key(word) == Some(key) => kv_key(w) != Some(key),
+                _ => *w != word,
+            }
+        });
+        self.set_words(&words);
+    }
+
+    /// Replaces every word equal to `old` with `new`, an empty `new` removes the word.
+    /// Only the first `new` is kept if the task already had it, which merges the two
+    pub fn rename_word(&mut self, old: &str, new: &str) {
+        self.replace_words(|w| (w == old).then(|| new.to_string()));
+    }
+
+    /// Renames the project and every project nested under it, `+work` to `+job` turns
+    /// `+work.release` into `+job.release`. An empty `new` removes them all
+    pub fn rename_project(&mut self, old: &str, new: &str) {
+        self.replace_words(|w| {
+            let project = w.strip_prefix('+')?;
+            let old = old.strip_prefix('+')?;
+            if !is_subproject(project, old) {
+                None
+            } else if new.is_empty() {
+                Some(String::new())
+            } else {
+                Some(new.to_string() + &project[old.len()..])
+            }
+        });
+    }
+
+    /// replaces the words for which `replace` returns a value, dropping duplicates of the new words
+    fn replace_words(&mut self, replace: impl Fn(&str) -> Option<String>) {
+        let text = self.text.clone();
+        let replaced: Vec<(String, bool)> = words(&text)
+            .into_iter()
+            .map(|w| match replace(w) {
+                Some(new) => (new, true),
+                None => (w.to_string(), false),
+            })
+            .collect();
+        let new_words: Vec<&str> = replaced
+            .iter()
+            .filter(|(_, is_new)| *is_new)
+            .map(|(w, _)| w.as_str())
+            .collect();
+        let mut kept: Vec<&str> = Vec::new();
+        for (word, _) in &replaced {
+            let duplicate = new_words.contains(&word.as_str()) && kept.contains(&word.as_str());
+            if !word.is_empty() && !duplicate {
+                kept.push(word);
+            }
+        }
+        self.set_words(&kept);
+    }
+
+    /// value of the first `key:value` word with the key, the key includes the `:`
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.text
+            .split_whitespace()
+            .find_map(|w| w.strip_prefix(key))
+            .filter(|v| !v.is_empty())
+    }
+
+    /// the `id:` other tasks use to refer to this task
+    pub fn id(&self) -> Option<&str> {
+        self.value(ID_KEY)
+    }
+
+    /// the id of the parent task from a `parent:` or `p:` key
+    pub fn parent(&self) -> Option<&str> {
+        PARENT_KEYS.iter().find_map(|key| self.value(key))
+    }
+
+    /// the file of the note of the task, relative to the notes directory.
+    /// A path leaving the notes directory isn't a note
+    pub fn note(&self) -> Option<&str> {
+        self.value(NOTE_KEY).filter(|file| {
+            Path::new(file)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        })
+    }
+
+    /// the words of the description, leaving out the priority, dates, tags and key values
+    pub fn description_words(&self) -> Vec<&str> {
+        self.arr
+            .iter()
+            .skip(1)
+            .filter(|section| matches!(section.0, TaskStringTag::Other))
+            .flat_map(|section| section.1.split_whitespace())
+            .filter(|w| !w.chars().all(|c| c.is_ascii_digit() || c == '-'))
+            .collect()
+    }
+
+    /// the ids of the tasks this task waits for, from `dep:` keys which can list several ids
+    /// like `dep:3,4`
+    pub fn dependencies(&self) -> impl Iterator<Item = &str> {
+        self.text
+            .split_whitespace()
+            .filter_map(|w| w.strip_prefix(DEPENDENCY_KEY))
+            .flat_map(|ids| ids.split(','))
+            .filter(|id| !id.is_empty())
+    }
+
+    /// the creation and the completion date of the task, a done task with a single date
+    /// only has a completion date
+    pub fn dates(&self) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let parse = |date: &str| NaiveDate::parse_from_str(date, DATE_FORMAT_STR).ok();
+        if self.done {
+            let rest = self.text.strip_prefix(DONE_PREFIX).unwrap_or(&self.text);
+            let (rest, first) = get_date(rest.trim_start());
+            let (_, second) = get_date(rest.trim_start());
+            if second.is_empty() {
+                (None, parse(first))
+            } else {
+                (parse(second), parse(first))
+            }
+        } else {
+            let rest = self.text.strip_prefix(PENDING_PREFIX).unwrap_or(&self.text);
+            let (rest, _) = get_priority(rest);
+            let (_, created) = get_date(rest.trim_start());
+            (parse(created), None)
+        }
+    }
+
+    /// every `key:value` word of the task in the order they're written
+    pub fn key_values(&self) -> Vec<(&str, &str)> {
+        words(&self.text)
+            .into_iter()
+            .filter_map(|w| kv_key(w).map(|key| (key, &w[key.len() + 1..])))
+            .collect()
+    }
+
+    /// the date of the `due:` key
+    pub fn due(&self) -> Option<NaiveDate> {
+        self.value(DUE_KEY)
+            .and_then(|due| NaiveDate::parse_from_str(due, DATE_FORMAT_STR).ok())
+    }
+
+    /// the `rec:` interval of a recurring task like `1w` or `+2m`
+    pub fn recurrence(&self) -> Option<&str> {
+        self.value(REC_KEY)
+    }
+
+    /// the due date the next task gets when this recurring task is completed
+    pub fn next_due(&self) -> Option<String> {
+        try_rec(&self.text).map(|(_, new)| new)
+    }
+
+    /// the `+project` words of the task without the prefix
+    pub fn projects(&self) -> impl Iterator<Item = &str> {
+        self.text
+            .split_whitespace()
+            .filter_map(|w| w.strip_prefix('+'))
+            .filter(|w| !w.is_empty())
+    }
+
+    /// the `@context` words of the task without the prefix
+    pub fn contexts(&self) -> impl Iterator<Item = &str> {
+        self.text
+            .split_whitespace()
+            .filter_map(|w| w.strip_prefix('@'))
+            .filter(|w| !w.is_empty())
+    }
+
+    /// moves the priority one step towards `A`, a task without a priority gets `Z`
+    pub fn raise_priority(&mut self) {
+        let priority = match self.priority() {
+            None => 'Z',
+            Some('A') => 'A',
+            Some(pri) => (pri as u8 - 1) as char,
+        };
+        self.set_priority(Some(priority));
+    }
+
+    /// moves the priority one step towards `Z`, lowering `Z` clears the priority
+    pub fn lower_priority(&mut self) {
+        let priority = match self.priority() {
+            None | Some('Z') => None,
+            Some(pri) => Some((pri as u8 + 1) as char),
+        };
+        self.set_priority(priority);
+    }
+}
+
+/// `project` is `parent` itself or nested under it, like `work.release` under `work`
+pub fn is_subproject(project: &str, parent: &str) -> bool {
+    project == parent
+        || (project.starts_with(parent)
+            && !parent.is_empty()
+            && project[parent.len()..].starts_with(PROJECT_SEPARATORS))
+}
+
+/// the parents of a nested project, `work` and `work.release` for `work.release.v2`
+pub fn project_parents(project: &str) -> impl Iterator<Item = &str> {
+    project
+        .match_indices(PROJECT_SEPARATORS)
+        .map(|(index, _)| &project[..index])
+        .filter(|parent| !parent.is_empty())
+}
+
+/// the segments of a nested project, used to sort the projects as a tree
+pub fn project_segments(project: &str) -> Vec<&str> {
+    project.split(PROJECT_SEPARATORS).collect()
+}
+
+/// Completes the typed text to the project one segment at a time,
+/// so `work.` completes to `work.release` for the project `work.release.v2`
+pub fn complete_project(project: &str, typed: &str) -> Option<String> {
+    let parent_len = typed
+        .rfind(PROJECT_SEPARATORS)
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let (parent, partial) = typed.split_at(parent_len);
+    let rest = project.strip_prefix(parent)?;
+    let segment = rest.split(PROJECT_SEPARATORS).next()?;
+    if segment.is_empty() || !segment.contains(partial) {
+        None
+    } else {
+        Some(parent.to_string() + segment)
+    }
+}
+
+/// words of the task text without the pending prefix
+fn words(text: &str) -> Vec<&str> {
+    text.strip_prefix(PENDING_PREFIX)
+        .unwrap_or(text)
+        .split_whitespace()
+        .collect()
+}
+
+/// the line with the date as its creation date, after the priority if it has one.
+/// A line which already has a creation date is left as it is
+pub fn with_creation_date(line: &str, date: &str) -> String {
+    let line = line.trim();
+    let (rest, priority) = get_priority(line);
+    let (_, created) = get_date(rest.trim_start());
+    if !created.is_empty() || line.starts_with(DONE_PREFIX) {
+        line.to_string()
+    } else if priority.is_empty() {
+        format!("{date} {line}")
+    } else {
+        format!("({priority}) {date} {}", rest.trim_start())
+    }
+}
+
+/// What the tasks can be sorted by, the tasks without the value go last
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Priority,
+    Due,
+    Created,
+    Description,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 4] = [
+        SortKey::Priority,
+        SortKey::Due,
+        SortKey::Created,
+        SortKey::Description,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Priority => "priority",
+            SortKey::Due => "due",
+            SortKey::Created => "created",
+            SortKey::Description => "description",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|key| key.name() == name)
+    }
+
+    pub fn compare(self, a: &Task, b: &Task) -> std::cmp::Ordering {
+        // None sorts before Some, so the missing values are flipped to go last
+        fn last<T: Ord>(a: Option<T>, b: Option<T>) -> std::cmp::Ordering {
+            a.is_none().cmp(&b.is_none()).then(a.cmp(&b))
+        }
+        match self {
+            SortKey::Priority => last(a.priority(), b.priority()),
+            SortKey::Due => last(a.due(), b.due()),
+            SortKey::Created => last(a.dates().0, b.dates().0),
+            SortKey::Description => a
+                .description_words()
+                .join(" ")
+                .to_lowercase()
+                .cmp(&b.description_words().join(" ").to_lowercase()),
+        }
+    }
+}
+
+/// key of a `key:value` word, projects and contexts are never key values
+fn kv_key(word: &str) -> Option<&str> {
+    if word.starts_with('+') || word.starts_with('@') {
+        None
+    } else {
+        word.split_once(':')
+            .map(|(key, _)| key)
+            .filter(|key| !key.is_empty())
+    }
 }
 
 fn get_priority(input: &str) -> (&str, &str) {
@@ -149,16 +571,8 @@ fn get_date(input: &str) -> (&str, &str) {
 }
 
 fn try_rec(input: &str) -> Option<(String, String)> {
-    if let Some(rec) = input
-        .split_whitespace()
-        .filter(|e| e.starts_with(REC_KEY))
-        .last()
-    {
-        if let Some(old_date_str) = input
-            .split_whitespace()
-            .filter(|e| e.starts_with(DUE_KEY))
-            .last()
-        {
+    if let Some(rec) = input.split_whitespace().rfind(|e| e.starts_with(REC_KEY)) {
+        if let Some(old_date_str) = input.split_whitespace().rfind(|e| e.starts_with(DUE_KEY)) {
             let old_date_str = old_date_str.strip_prefix(DUE_KEY).unwrap();
             let rec = rec.strip_prefix(REC_KEY).unwrap();
             if let Some((strict, num, duration)) = parse_rec(rec) {
@@ -192,10 +606,7 @@ fn try_rec(input: &str) -> Option<(String, String)> {
                     // => old_date.checked_add_days(Days::new(num)),
                     _ => old_date.checked_add_days(Days::new(num)),
                 };
-                let new_date = match new_date {
-                    Some(d) => d,
-                    None => return None,
-                };
+                let new_date = new_date?;
 
                 let new_date_str = new_date.format_with_items(DATE_FORMAT_CONST).to_string();
                 Some((old_date_str.to_owned(), new_date_str))
@@ -270,7 +681,7 @@ fn parse_task(text: &str) -> Vec<TaskSection> {
             }
             arr.push(TaskSection(TaskStringTag::KV, " ".to_string() + word));
         } else {
-            current_state.push_str(" ");
+            current_state.push(' ');
             current_state.push_str(word);
         }
     }
@@ -284,14 +695,17 @@ fn parse_task(text: &str) -> Vec<TaskSection> {
 #[cfg(test)]
 mod test {
 
-    use crate::tasks::{Task, PENDING_PREFIX};
+    use crate::tasks::{
+        complete_project, is_subproject, project_parents, with_creation_date, SortKey, Task,
+        PENDING_PREFIX,
+    };
     use chrono::{Days, Local, Months, NaiveDate};
 
     use super::DATE_FORMAT_CONST;
 
     #[test]
     fn simple_tasks() {
-        let list: Vec<String> = vec![
+        let list: Vec<String> = [
             "task",
             "x done",
             "(A) task with priority",
@@ -308,7 +722,7 @@ mod test {
         })
         .collect();
 
-        let expected: Vec<String> = vec![
+        let expected: Vec<String> = [
             "x task",
             "done",
             "x task with priority Pri:A",
@@ -325,7 +739,7 @@ mod test {
 
     #[test]
     fn tasks_with_date() {
-        let list: Vec<String> = vec![
+        let list: Vec<String> = [
             "  2024-08-14   task with start date",
             "x  2024-08-15  2024-08-14  done task with start date",
             "(A)   2024-08-14   task with priority and start date",
@@ -345,7 +759,7 @@ mod test {
         let local = Local::now();
         let date = local.format_with_items(DATE_FORMAT_CONST).to_string();
 
-        let expected: Vec<String> = vec![
+        let expected: Vec<String> = [
             &format!("x {date} 2024-08-14 task with start date"),
             "2024-08-14 done task with start date",
             &format!("x {date} 2024-08-14 task with priority and start date Pri:A"),
@@ -369,7 +783,7 @@ mod test {
             .format_with_items(DATE_FORMAT_CONST)
             .to_string();
 
-        let list: Vec<(String, String)> = vec![
+        let list: Vec<(String, String)> = [
             format!("recurrent task rec:10 due:{due}"),
             format!("strict recurrent task rec:+10 due:{due}"),
             format!("recurrent task with days rec:10d due:{due}"),
@@ -382,7 +796,7 @@ mod test {
             format!("2024-08-27 strict recurrent task with start dates rec:+10 due:{due}"),
         ]
         .iter()
-        .map(|t| Task::new(&t))
+        .map(|t| Task::new(t))
         .map(|mut t1| {
             let t2 = t1.toggle_done().unwrap();
             (t1.text.to_string(), t2)
@@ -466,4 +880,175 @@ mod test {
             .zip(expected)
             .for_each(|e| assert_eq!(*e.0, e.1));
     }
+
+    #[test]
+    fn tasks_priority() {
+        let mut task = Task::new("2024-08-14 task with start date");
+        task.set_priority(Some('b'));
+        assert_eq!(
+            task.text,
+            format!("{PENDING_PREFIX}(B) 2024-08-14 task with start date")
+        );
+        assert_eq!(task.priority(), Some('B'));
+
+        task.raise_priority();
+        task.raise_priority();
+        assert_eq!(
+            task.text,
+            format!("{PENDING_PREFIX}(A) 2024-08-14 task with start date")
+        );
+
+        task.set_priority(None);
+        assert_eq!(
+            task.text,
+            format!("{PENDING_PREFIX}2024-08-14 task with start date")
+        );
+        task.raise_priority();
+        assert_eq!(task.priority(), Some('Z'));
+        task.lower_priority();
+        assert_eq!(task.priority(), None);
+
+        // a done task keeps the priority in the Pri key, so it survives toggling back
+        let mut task = Task::new("x 2024-08-21 2024-08-14 done task Pri:A");
+        task.set_priority(Some('C'));
+        assert_eq!(task.text, "x 2024-08-21 2024-08-14 done task Pri:C");
+        assert_eq!(task.priority(), Some('C'));
+        task.toggle_done();
+        assert_eq!(
+            task.text,
+            format!("{PENDING_PREFIX}(C) 2024-08-14 done task")
+        );
+    }
+
+    #[test]
+    fn tasks_words() {
+        let mut task = Task::new("(A) task +old @home due:2024-08-14");
+        task.add_word("+new");
+        task.add_word("@home");
+        task.add_word("due:2024-09-01");
+        task.remove_word("+old");
+        assert_eq!(
+            task.text,
+            format!("{PENDING_PREFIX}(A) task @home due:2024-09-01 +new")
+        );
+
+        task.remove_word("due:");
+        assert_eq!(task.text, format!("{PENDING_PREFIX}(A) task @home +new"));
+
+        let mut task = Task::new("x 2024-08-21 done task Pri:A");
+        task.remove_word("x");
+        task.add_word("+project");
+        assert_eq!(task.text, "x 2024-08-21 done task Pri:A +project");
+    }
+
+    #[test]
+    fn tasks_rename_word() {
+        let mut task = Task::new("task +work +workshop @home");
+        task.rename_word("+work", "+job");
+        assert_eq!(
+            task.text,
+            format!("{PENDING_PREFIX}task +job +workshop @home")
+        );
+
+        // merging into a project the task already has keeps one of them
+        task.rename_word("+workshop", "+job");
+        assert_eq!(task.text, format!("{PENDING_PREFIX}task +job @home"));
+
+        task.rename_word("@home", "");
+        assert_eq!(task.text, format!("{PENDING_PREFIX}task +job"));
+        assert_eq!(task.projects().collect::<Vec<&str>>(), vec!["job"]);
+    }
+
+    #[test]
+    fn tasks_details() {
+        let task = Task::new("(A) 2024-08-27 write docs +rtodo due:2024-09-01 rec:+1w");
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+        assert_eq!(task.dates(), (date("2024-08-27"), None));
+        assert_eq!(
+            task.key_values(),
+            vec![("due", "2024-09-01"), ("rec", "+1w")]
+        );
+        assert_eq!(task.next_due().as_deref(), Some("2024-09-08"));
+
+        let task = Task::new("x 2024-09-02 2024-08-27 write docs");
+        assert_eq!(task.dates(), (date("2024-08-27"), date("2024-09-02")));
+        let task = Task::new("x 2024-09-02 write docs");
+        assert_eq!(task.dates(), (None, date("2024-09-02")));
+        assert_eq!(task.next_due(), None);
+    }
+
+    #[test]
+    fn tasks_creation_date() {
+        let date = "2024-09-01";
+        assert_eq!(with_creation_date("plan", date), "2024-09-01 plan");
+        assert_eq!(with_creation_date("(B) plan", date), "(B) 2024-09-01 plan");
+        assert_eq!(
+            with_creation_date("2024-08-01 plan", date),
+            "2024-08-01 plan"
+        );
+        assert_eq!(with_creation_date("x done", date), "x done");
+    }
+
+    #[test]
+    fn nested_projects() {
+        assert!(is_subproject("work.release.v2", "work"));
+        assert!(is_subproject("work/release", "work"));
+        assert!(is_subproject("work", "work"));
+        assert!(!is_subproject("workshop", "work"));
+        assert_eq!(
+            project_parents("work.release/v2").collect::<Vec<&str>>(),
+            vec!["work", "work.release"]
+        );
+        assert_eq!(
+            complete_project("work.release.v2", "wo"),
+            Some("work".to_string())
+        );
+        assert_eq!(
+            complete_project("work.release.v2", "work.re"),
+            Some("work.release".to_string())
+        );
+        assert_eq!(complete_project("work.release.v2", "home"), None);
+
+        let mut task = Task::new("task +work +work.release +workshop");
+        task.rename_project("+work", "+job");
+        assert_eq!(
+            task.text,
+            format!("{PENDING_PREFIX}task +job +job.release +workshop")
+        );
+        task.rename_project("+job", "");
+        assert_eq!(task.text, format!("{PENDING_PREFIX}task +workshop"));
+    }
+
+    #[test]
+    fn tasks_sort_keys() {
+        let mut tasks: Vec<Task> = [
+            "write report due:2024-03-01",
+            "(B) call bob",
+            "(A) 2024-01-05 pay rent due:2024-02-01",
+        ]
+        .iter()
+        .map(|t| Task::new(t))
+        .collect();
+        tasks.sort_by(|a, b| SortKey::Priority.compare(a, b));
+        assert_eq!(tasks[0].priority(), Some('A'));
+        assert_eq!(tasks[2].priority(), None);
+        tasks.sort_by(|a, b| SortKey::Due.compare(a, b));
+        assert_eq!(tasks[0].description_words(), ["pay", "rent"]);
+        assert_eq!(tasks[2].description_words(), ["call", "bob"]);
+        tasks.sort_by(|a, b| SortKey::Description.compare(a, b));
+        assert_eq!(tasks[0].description_words(), ["call", "bob"]);
+    }
+
+    #[test]
+    fn task_notes() {
+        assert_eq!(Task::new("task note:plan.md").note(), Some("plan.md"));
+        assert_eq!(
+            Task::new("task note:work/plan.md").note(),
+            Some("work/plan.md")
+        );
+        assert_eq!(Task::new("task note:/etc/passwd").note(), None);
+        assert_eq!(Task::new("task note:../../.ssh/config").note(), None);
+        assert_eq!(Task::new("task note:a/../../b").note(), None);
+        assert_eq!(Task::new("task note:./plan.md").note(), Some("./plan.md"));
+    }
 }
@@ -3,7 +3,9 @@ use std::io::{self, stdout, Stdout};
 use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
-        event::{DisableBracketedPaste, EnableBracketedPaste},
+        event::{
+            DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        },
         execute,
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     },
@@ -12,14 +14,19 @@ use ratatui::{
 
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
-pub fn init() -> io::Result<Tui> {
+/// starts the TUI, the mouse events are captured when `mouse` is set
+pub fn init(mouse: bool) -> io::Result<Tui> {
     execute!(stdout(), EnterAlternateScreen)?;
     execute!(stdout(), EnableBracketedPaste)?;
+    if mouse {
+        execute!(stdout(), EnableMouseCapture)?;
+    }
     enable_raw_mode()?;
     Terminal::new(CrosstermBackend::new(stdout()))
 }
 
 pub fn restore() -> io::Result<()> {
+    execute!(stdout(), DisableMouseCapture)?;
     execute!(stdout(), DisableBracketedPaste)?;
     execute!(stdout(), LeaveAlternateScreen)?;
     disable_raw_mode()?;
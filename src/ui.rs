@@ -1,18 +1,47 @@
+use chrono::Local;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Position, Rect},
-    style::{Style, Styled, Stylize},
+    style::{Color, Modifier, Style, Styled, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, Padding, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, Padding, Paragraph, Wrap},
     Frame,
 };
 const SPACE_2: &str = "  ";
+/// lines of a note shown in the detail pane
+const NOTE_PREVIEW_LINES: usize = 10;
+/// the smallest terminal the tasks are drawn in, smaller ones only get a notice
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 12;
+/// the narrowest the task list gets, the sidebar and the detail pane are hidden before that
+const MIN_LIST_WIDTH: u16 = 40;
+const MIN_DETAIL_WIDTH: u16 = 24;
+/// rows of the list and of the detail pane when it's at the bottom
+const MIN_PANE_HEIGHT: u16 = 6;
+/// popups take at least this much of the terminal, or all of it when it's smaller
+const POPUP_MIN_WIDTH: u16 = 40;
+const POPUP_MIN_HEIGHT: u16 = 8;
 
 use crate::{
-    app::{AppState, Autocomplete, InputState, Model},
-    tasks::TaskStringTag,
+    app::{
+        AppState, Autocomplete, Capture, Focus, InputState, Model, SubstitutePreview, TagManager,
+    },
+    config::{DetailPosition, Overflow, Theme},
+    keymap::Action,
+    mouse::{MouseAreas, RowArea},
+    notices::Level,
+    overflow,
+    palette::Palette,
+    tasks::{Task, TaskStringTag, PROJECT_SEPARATORS},
+    vim::Mode,
 };
 
 pub fn view(model: &mut Model, f: &mut Frame<'_>) {
+    if f.area().width < MIN_WIDTH || f.area().height < MIN_HEIGHT {
+        render_too_small(f);
+        // nothing can be clicked until the tasks are drawn again
+        model.mouse_areas = MouseAreas::default();
+        return;
+    }
     let outer_block = Block::new().padding(Padding::uniform(1));
 
     let inner_area = outer_block.inner(f.area());
@@ -21,15 +50,58 @@ pub fn view(model: &mut Model, f: &mut Frame<'_>) {
         .split(inner_area);
 
     if let AppState::Help = model.app_state {
-        render_help_view(f, &chunks);
+        render_help_view(model, f, &chunks);
     } else {
-        render_task_list(&chunks, f, model);
+        let sidebar_fits = chunks[1].width >= model.config.sidebar_width + MIN_LIST_WIDTH;
+        if !sidebar_fits && model.focus == Focus::Sidebar {
+            model.focus = Focus::List;
+        }
+        let list_area = if model.sidebar.visible && sidebar_fits {
+            let layout = Layout::new(
+                Direction::Horizontal,
+                [
+                    Constraint::Length(model.config.sidebar_width),
+                    Constraint::Min(10),
+                ],
+            )
+            .split(chunks[1]);
+            render_sidebar(model, layout[0], f);
+            layout[1]
+        } else {
+            chunks[1]
+        };
+        let size = model.config.detail_size.min(90);
+        let (direction, detail_fits) = match model.config.detail_position {
+            DetailPosition::Right => (
+                Direction::Horizontal,
+                fits(list_area.width, size, MIN_LIST_WIDTH, MIN_DETAIL_WIDTH),
+            ),
+            DetailPosition::Bottom => (
+                Direction::Vertical,
+                fits(list_area.height, size, MIN_PANE_HEIGHT, MIN_PANE_HEIGHT),
+            ),
+        };
+        if model.detail && detail_fits {
+            let layout = Layout::new(
+                direction,
+                [
+                    Constraint::Percentage(100 - size),
+                    Constraint::Percentage(size),
+                ],
+            )
+            .split(list_area);
+            render_task_list(layout[0], f, model);
+            model.load_note_preview();
+            render_detail(model, layout[1], f);
+        } else {
+            render_task_list(list_area, f, model);
+        }
         render_saved_searches_list(model, &chunks, f);
 
         match model.app_state {
             AppState::Edit(_) => render_input(&chunks, model, f),
             AppState::Report => {
-                let rect = centered_rect(50, 30, chunks[1]);
+                let rect = centered_rect(50, 60, chunks[1]);
                 let block = Block::default()
                     .borders(Borders::ALL)
                     .title_top("Report")
@@ -38,6 +110,18 @@ pub fn view(model: &mut Model, f: &mut Frame<'_>) {
                 f.render_widget(Clear, rect);
                 f.render_widget(para, rect);
             }
+            AppState::Substitute(ref mut preview) => {
+                render_substitute_preview(preview, &model.config.theme, &chunks, f)
+            }
+            AppState::Tags(ref mut manager) => {
+                render_tag_manager(manager, &model.config.theme, &chunks, f)
+            }
+            AppState::Capture(ref capture) => {
+                render_capture(capture, &model.config.theme, &chunks, f)
+            }
+            AppState::Palette(ref mut palette) => {
+                render_palette(palette, &model.config.theme, &chunks, f)
+            }
             _ => {}
         };
 
@@ -50,36 +134,72 @@ pub fn view(model: &mut Model, f: &mut Frame<'_>) {
 
         // Render this last so that Autocomplete rendering works:w:w
         if let AppState::Goto(ref num) = model.app_state {
-            render_goto_statusline(num, f, &chunks)
+            render_goto_statusline(model, num, f, &chunks)
+        } else if let AppState::Confirm(ref confirm) = model.app_state {
+            render_confirm_statusline(&confirm.prompt, f, &chunks)
         } else {
-            render_statusline(f, &chunks);
+            render_statusline(model, f, &chunks);
         }
     }
 }
 
-fn render_help_view(f: &mut Frame, chunks: &std::rc::Rc<[Rect]>) {
+/// whether both panes of a split keep their minimum size, the second pane takes `percent`
+fn fits(length: u16, percent: u16, first_min: u16, second_min: u16) -> bool {
+    let second = length as u32 * percent as u32 / 100;
+    let first = length as u32 - second;
+    first >= first_min as u32 && second >= second_min as u32
+}
+
+fn render_too_small(f: &mut Frame<'_>) {
+    let area = f.area();
+    let text = vec![
+        Line::from("Terminal too small"),
+        Line::styled(
+            format!(
+                "{}x{}, needs at least {MIN_WIDTH}x{MIN_HEIGHT}",
+                area.width, area.height
+            ),
+            Style::new().dim(),
+        ),
+    ];
+    let y = area.height.saturating_sub(2) / 2;
+    let rect = Rect::new(area.x, area.y + y, area.width, area.height - y);
+    let para = Paragraph::new(text)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(para, rect);
+}
+
+fn render_help_view(model: &Model, f: &mut Frame, chunks: &std::rc::Rc<[Rect]>) {
     let help_block = Block::new().borders(Borders::BOTTOM | Borders::TOP);
+    let keymap = &model.keymap;
+    let mut text: Vec<String> = Action::ALL
+        .iter()
+        .filter(|action| !keymap.keys(**action).is_empty())
+        .map(|action| format!("{} - {}", keymap.key_names(*action), action.description()))
+        .collect();
+    text.push("A-Z - Set the priority of the task, with the letters not bound above".to_string());
+    text.push("/blocked:yes or /blocked:no - Show the blocked or the unblocked tasks".to_string());
     let p = Paragraph::new(
-        "d or space - Toggle Done for the Task
-x - Delete Task
-j or 🡣 - Move to next task
-k or 🡩 - Move to prev task
-n - Start writing a new task
-e - Edit the current task
-c - Copy this task and open the editor modal
-r - open the report window
-Ctrl+d - Clear out the current input
-/ - start the search input
-l - load a search
-a - save a search to be reused later
-q - quit
-Q - quit without saving any changes
-s - Save the current state to disk
-~ - Help
-: - Goto mode similar to vim or helix
+        text.join("\n")
+            + "
+
+With marked tasks the changes act on all of them
+
+Sidebar
+Enter or space - Filter on the project or context, several combine
+c - Clear the filter
 
 Editing
-Ctrl + d - Clear out the current text",
+Ctrl + d - Clear out the current text
+Up or Down - Go through the tasks or searches entered before
+Alt+b or Alt+f - Move back or forward a word
+Ctrl+w or Alt+d - Delete the word before or after the cursor
+Ctrl+k or Ctrl+u - Delete to the end or the start of the text
+Ctrl+y - Put back the text deleted last
+With vim_mode Esc goes to normal mode, where h l w b e 0 $ move, i a I A insert,
+x D dd cc cw ciw dw diw change the text and . repeats the last change
+Pasting works in every input, several lines are joined into one",
     )
     .block(help_block);
     f.render_widget(p, chunks[1]);
@@ -93,44 +213,113 @@ Ctrl + d - Clear out the current text",
     );
 }
 
-fn render_statusline(f: &mut Frame<'_>, chunks: &std::rc::Rc<[Rect]>) {
+fn render_statusline(model: &Model, f: &mut Frame<'_>, chunks: &std::rc::Rc<[Rect]>) {
     let options = [
-        " ~: Help ",
-        SPACE_2,
-        " d: Toggle ",
-        SPACE_2,
-        " e: Edit ",
-        SPACE_2,
-        " n: New Task ",
-        SPACE_2,
-        " q: Quit ",
-        SPACE_2,
-        " /: Search ",
-        SPACE_2,
-        " x: Delete ",
-        SPACE_2,
-        " l: load Search",
-        SPACE_2,
-        " a: Save Search",
-        SPACE_2,
-        " r: Report",
+        (Action::Help, "Help"),
+        (Action::ToggleDone, "Toggle"),
+        (Action::Edit, "Edit"),
+        (Action::NewTask, "New Task"),
+        (Action::Quit, "Quit"),
+        (Action::Search, "Search"),
+        (Action::Delete, "Delete"),
+        (Action::LoadSearch, "load Search"),
+        (Action::SaveSearch, "Save Search"),
+        (Action::Report, "Report"),
     ];
 
-    let line = options
-        .iter()
-        .map(|a| {
-            if **a != *SPACE_2 {
-                Span::styled(*a, Style::default().on_gray().black())
-            } else {
-                Span::raw(SPACE_2)
-            }
-        })
-        .collect::<Vec<Span>>();
+    let mut line = Vec::new();
+    if model.config.vim_mode && matches!(model.app_state, AppState::Edit(_) | AppState::SearchInput)
+    {
+        let mode = match model.vim.mode {
+            Mode::Insert => "INSERT",
+            Mode::Normal => "NORMAL",
+        };
+        line.push(Span::styled(
+            format!(" {mode} {}", model.vim.pending()),
+            Style::default().on_gray().black().bold(),
+        ));
+        line.push(Span::raw(SPACE_2));
+    }
+    if model.dirty {
+        line.push(Span::styled(
+            " [+] ",
+            Style::default().on_gray().black().bold(),
+        ));
+        line.push(Span::raw(SPACE_2));
+    }
+    if !model.pending.typed.is_empty() {
+        line.push(Span::styled(
+            format!(" {} ", model.pending.typed),
+            Style::default().on_gray().black().bold(),
+        ));
+        line.push(Span::raw(SPACE_2));
+    }
+    if model.marked_count() > 0 || model.mark_anchor.is_some() {
+        line.push(Span::styled(
+            format!(" {} marked ", model.marked_count()),
+            Style::default().bg(model.config.theme.marked),
+        ));
+        line.push(Span::raw(SPACE_2));
+    }
+    if let Some(notice) = model.notices.current() {
+        let theme = &model.config.theme;
+        let color = match notice.level {
+            Level::Info => theme.info,
+            Level::Warn => theme.warn,
+            Level::Error => theme.error,
+        };
+        line.push(Span::styled(
+            format!(" {} ", notice.text),
+            Style::default().bg(color).white(),
+        ));
+        line.push(Span::raw(SPACE_2));
+    }
+    if let Some(cycle) = model.dependency_cycles.first() {
+        line.push(Span::styled(
+            format!(" dependency cycle: {} ", cycle.join(" -> ")),
+            Style::default().on_red().white(),
+        ));
+        line.push(Span::raw(SPACE_2));
+    }
+    for (action, label) in options {
+        // only the first key of the action is shown to keep the line short
+        if let Some(key) = model.keymap.keys(action).first() {
+            line.push(Span::styled(
+                format!(" {key}: {label} "),
+                Style::default().on_gray().black(),
+            ));
+            line.push(Span::raw(SPACE_2));
+        }
+    }
+    line.pop();
     f.render_widget(Line::from(line), chunks[2]);
 }
 
-fn render_goto_statusline(num: &String, f: &mut Frame<'_>, chunks: &std::rc::Rc<[Rect]>) {
-    let line = Span::from(":".to_string() + num);
+fn render_goto_statusline(
+    model: &Model,
+    command: &str,
+    f: &mut Frame<'_>,
+    chunks: &std::rc::Rc<[Rect]>,
+) {
+    let mut line = vec![Span::from(":".to_string() + command)];
+    // the choices Tab can't decide between are listed after the command
+    let completions = &model.command_completions;
+    if completions.len() > 1 {
+        line.push(Span::raw(SPACE_2));
+        line.push(Span::styled(completions.join("  "), Style::new().dim()));
+    }
+    f.render_widget(Line::from(line), chunks[2]);
+}
+
+fn render_confirm_statusline(prompt: &str, f: &mut Frame<'_>, chunks: &std::rc::Rc<[Rect]>) {
+    let line = Line::from(vec![
+        Span::raw(format!("{prompt} ")),
+        Span::styled(" y: Yes ", Style::default().on_gray().black()),
+        Span::raw(SPACE_2),
+        Span::styled(" n: No ", Style::default().on_gray().black()),
+        Span::raw(SPACE_2),
+        Span::styled(" Esc: Cancel ", Style::default().on_gray().black()),
+    ]);
     f.render_widget(line, chunks[2]);
 }
 
@@ -147,9 +336,15 @@ fn render_static_search_input(model: &mut Model, f: &mut Frame<'_>, layout: Rect
 }
 
 fn render_active_search_input(model: &mut Model, f: &mut Frame<'_>, layout: Rect) {
-    let input_widget = Paragraph::new(model.search.input.value()).block(Block::new());
+    let scroll = model
+        .search
+        .input
+        .visual_scroll(layout.width.max(1) as usize - 1);
+    let input_widget = Paragraph::new(model.search.input.value())
+        .scroll((0, scroll as u16))
+        .block(Block::new());
     f.render_widget(input_widget, layout);
-    let cursor_x = layout.x + model.search.input.visual_cursor() as u16;
+    let cursor_x = layout.x + (model.search.input.visual_cursor().max(scroll) - scroll) as u16;
     //     // Move one line down, from the border to the input line
     f.set_cursor_position(Position::new(cursor_x, layout.y));
     render_autocomplete(&mut model.auto_complete, cursor_x, layout, true, f);
@@ -163,6 +358,8 @@ fn render_input(chunks: &std::rc::Rc<[Rect]>, model: &mut Model, f: &mut Frame<'
         AppState::Edit(ref state) => match state {
             InputState::Edit => "Edit Task",
             InputState::NewTask | InputState::CopyTask => "New Task",
+            InputState::Subtask(_) => "New Subtask",
+            InputState::Modify => "Modify Tasks (+project @context key:value, - to remove)",
         },
         _ => unreachable!(),
     };
@@ -184,47 +381,390 @@ fn render_input(chunks: &std::rc::Rc<[Rect]>, model: &mut Model, f: &mut Frame<'
     render_autocomplete(&mut model.auto_complete, cursor_x, layout, false, f);
 }
 
-fn render_task_list(chunks: &std::rc::Rc<[Rect]>, f: &mut Frame<'_>, model: &mut Model) {
+fn render_palette(
+    palette: &mut Palette,
+    theme: &Theme,
+    chunks: &std::rc::Rc<[Rect]>,
+    f: &mut Frame<'_>,
+) {
+    let rect = centered_rect(60, 60, chunks[1]);
+    let block = Block::bordered()
+        .title_top("Command Palette")
+        .title_bottom(" Enter: Run  Esc: Cancel ")
+        .title_alignment(ratatui::layout::Alignment::Center);
+    let inner = block.inner(rect);
+    f.render_widget(Clear, rect);
+    f.render_widget(block, rect);
+    let layout = Layout::new(
+        Direction::Vertical,
+        [Constraint::Length(2), Constraint::Min(1)],
+    )
+    .split(inner);
+
+    let width = layout[0].width.saturating_sub(2) as usize;
+    let scroll = palette.input.visual_scroll(width);
+    let input = Paragraph::new(Line::from(vec![
+        Span::raw("› "),
+        Span::styled(
+            palette
+                .input
+                .value()
+                .chars()
+                .skip(scroll)
+                .collect::<String>(),
+            theme.text,
+        ),
+    ]))
+    .block(Block::new().borders(Borders::BOTTOM));
+    f.render_widget(input, layout[0]);
+
+    let width = layout[1].width as usize;
+    let items: Vec<ListItem> = palette
+        .matches()
+        .iter()
+        .map(|entry| {
+            // the keys are shown at the right end of the line
+            let gap = width
+                .saturating_sub(entry.label.chars().count() + entry.keys.chars().count())
+                .max(1);
+            ListItem::new(Line::from(vec![
+                Span::styled(entry.label.clone(), theme.text),
+                Span::raw(" ".repeat(gap)),
+                Span::styled(entry.keys.clone(), Style::new().dim()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(theme.selected);
+    f.render_stateful_widget(list, layout[1], &mut palette.list_state);
+
+    let x = 2 + palette.input.visual_cursor().max(scroll) - scroll;
+    f.set_cursor_position(Position::new(layout[0].x + x as u16, layout[0].y));
+}
+
+fn render_capture(
+    capture: &Capture,
+    theme: &Theme,
+    chunks: &std::rc::Rc<[Rect]>,
+    f: &mut Frame<'_>,
+) {
+    let rect = centered_rect(60, 60, chunks[1]);
+    let block = Block::bordered()
+        .title_top("Capture Tasks")
+        .title_bottom(" Enter: New line  Tab: Suffix  Ctrl+s: Add all  Esc: Cancel ")
+        .title_alignment(ratatui::layout::Alignment::Center);
+    let inner = block.inner(rect);
+    f.render_widget(Clear, rect);
+    f.render_widget(block, rect);
+    let layout = Layout::new(
+        Direction::Vertical,
+        [Constraint::Min(1), Constraint::Length(2)],
+    )
+    .split(inner);
+
+    // keep the line being written in view when there are more lines than fit
+    let height = layout[0].height as usize;
+    let skip = (capture.lines.len() + 1).saturating_sub(height);
+    let width = layout[0].width.saturating_sub(2) as usize;
+    let scroll = capture.input.visual_scroll(width);
+    let mut lines: Vec<Line> = capture
+        .lines
+        .iter()
+        .skip(skip)
+        .map(|l| Line::from(vec![Span::raw("  "), Span::styled(l.as_str(), theme.text)]))
+        .collect();
+    lines.push(Line::from(vec![
+        Span::raw("› "),
+        Span::styled(
+            capture
+                .input
+                .value()
+                .chars()
+                .skip(scroll)
+                .collect::<String>(),
+            theme.text,
+        ),
+    ]));
+    f.render_widget(Paragraph::new(lines), layout[0]);
+
+    let label = "Add to every task: ";
+    let suffix = Paragraph::new(Line::from(vec![
+        Span::styled(label, Style::new().bold()),
+        Span::styled(capture.suffix.value(), theme.project),
+    ]))
+    .block(Block::new().borders(Borders::TOP));
+    f.render_widget(suffix, layout[1]);
+
+    let (x, y) = if capture.editing_suffix {
+        let x = label.len() + capture.suffix.visual_cursor();
+        (layout[1].x + x as u16, layout[1].y + 1)
+    } else {
+        let x = 2 + capture.input.visual_cursor().max(scroll) - scroll;
+        let y = (capture.lines.len() - skip).min(height.saturating_sub(1));
+        (layout[0].x + x as u16, layout[0].y + y as u16)
+    };
+    f.set_cursor_position(Position::new(x, y));
+}
+
+fn render_sidebar(model: &mut Model, area: Rect, f: &mut Frame<'_>) {
+    let theme = &model.config.theme;
+    let block = Block::new()
+        .borders(Borders::TOP | Borders::BOTTOM | Borders::RIGHT)
+        .title_top("open/done");
+    let width = block.inner(area).width as usize;
+    let entries = model.tag_counts();
+    let list = entries
+        .iter()
+        .map(|entry| {
+            let color = if entry.tag.starts_with('+') {
+                theme.project
+            } else {
+                theme.context
+            };
+            let active = model.has_search_term(&entry.tag);
+            let marker = if active { "● " } else { "  " };
+            // nested projects only show their last segment under the parent
+            let name = if entry.depth == 0 {
+                entry.tag.clone()
+            } else {
+                let segment = entry
+                    .tag
+                    .rsplit(PROJECT_SEPARATORS)
+                    .next()
+                    .unwrap_or(&entry.tag);
+                "  ".repeat(entry.depth) + segment
+            };
+            let open = entry.open.to_string();
+            let done = entry.done.to_string();
+            let used = marker.chars().count() + name.chars().count() + open.len() + done.len();
+            let gap = width.saturating_sub(used + 2);
+            let tag_style = if active {
+                Style::new().fg(color).bold()
+            } else {
+                Style::new().fg(color)
+            };
+            ListItem::from(Line::from(vec![
+                Span::styled(marker, color),
+                Span::styled(name, tag_style),
+                Span::raw(" ".repeat(gap)),
+                Span::styled(open, theme.text),
+                Span::styled("/", theme.completed_text),
+                Span::styled(done, theme.completed_text),
+                Span::raw(" "),
+            ]))
+        })
+        .collect::<List>()
+        .block(block);
+    let list = if model.focus == Focus::Sidebar {
+        list.highlight_style(theme.selected)
+    } else {
+        list
+    };
+    f.render_stateful_widget(list, area, &mut model.sidebar.list_state);
+}
+
+fn render_task_list(area: Rect, f: &mut Frame<'_>, model: &mut Model) {
     let list_block = Block::new().borders(Borders::BOTTOM | Borders::TOP);
+    // the borders take a row each
+    model.list_height = area.height.saturating_sub(2) as usize;
     let layout = Layout::new(
         Direction::Horizontal,
         [Constraint::Max(4), Constraint::Min(10)],
     )
-    .split(chunks[1]);
-    let (list, nums) = if model.search.input.value().is_empty() {
-        (&model.tasks, model.nums.as_slice())
-    } else {
-        (
-            &model.filtered_tasks,
-            &model.nums[0..model.filtered_tasks.len()],
-        )
+    .split(area);
+    let list = model.visible_tasks();
+    let nums = &model.nums[0..list.len()];
+
+    let marked_style = |index: usize| {
+        if model.is_marked(index) {
+            Style::new().bg(model.config.theme.marked)
+        } else {
+            Style::new()
+        }
     };
 
-    let nums_widget = List::new(nums.iter().map(|a| ListItem::from(Text::raw(a))))
-        .block(list_block.clone())
-        .highlight_style(model.config.theme.selected);
-    let list_widget = List::new(list.iter().map(|a| {
-        ListItem::from(Line::from(
-            a.arr
-                .iter()
-                .map(|a| {
-                    let color = match a.0 {
-                        TaskStringTag::Other => model.config.theme.text,
-                        TaskStringTag::Context => model.config.theme.context,
-                        TaskStringTag::Project => model.config.theme.project,
-                        TaskStringTag::Priority => model.config.theme.priority,
-                        TaskStringTag::KV => model.config.theme.kv,
-                    };
-                    Span::styled(a.1.as_str(), Style::new().set_style(color))
-                })
-                .collect::<Vec<Span>>(),
-        ))
+    let width = layout[1].width as usize;
+    let mut rows = Vec::with_capacity(list.len());
+    let items: Vec<ListItem> = list
+        .iter()
+        .enumerate()
+        .map(|(i, task)| {
+            let depth = model.list_depths.get(i).copied().unwrap_or(0);
+            let mut spans = vec![Span::raw("  ".repeat(depth))];
+            let progress = task.id().and_then(|id| {
+                let progress = model.subtask_progress.get(id)?;
+                Some((model.collapsed.contains(id), progress))
+            });
+            if let Some((collapsed, _)) = progress {
+                spans.push(Span::raw(if collapsed { "▸ " } else { "▾ " }));
+            }
+            let blocked = model.blocked.contains(&task.uid);
+            if blocked {
+                spans.push(Span::raw("⊘ "));
+            }
+            let prefix: usize = spans.iter().map(|span| span.width()).sum();
+            let mut row = row_area(layout[1].x + prefix as u16, task);
+            spans.extend(task.arr.iter().map(|a| {
+                let color = match a.0 {
+                    TaskStringTag::Other => model.config.theme.text,
+                    TaskStringTag::Context => model.config.theme.context,
+                    TaskStringTag::Project => model.config.theme.project,
+                    TaskStringTag::Priority => model.config.theme.priority,
+                    TaskStringTag::KV => model.config.theme.kv,
+                };
+                Span::styled(a.1.as_str(), Style::new().set_style(color))
+            }));
+            if task.note().is_some() {
+                spans.push(Span::raw(" ✎"));
+            }
+            if let Some((_, (done, total))) = progress {
+                spans.push(Span::styled(
+                    format!(" ({done}/{total})"),
+                    Style::new().set_style(model.config.theme.kv),
+                ));
+            }
+            let style = if blocked {
+                marked_style(i).add_modifier(Modifier::DIM)
+            } else {
+                marked_style(i)
+            };
+            let text = match model.config.overflow {
+                Overflow::Truncate => Text::from(Line::from(overflow::truncate(spans, width))),
+                Overflow::Wrap => {
+                    // the lines after the first start under the description
+                    let indent = prefix + row.checkbox.len();
+                    Text::from(overflow::wrap(&spans, width, indent))
+                }
+            };
+            row.lines = text.height() as u16;
+            rows.push(row);
+            ListItem::from(text).style(style)
+        })
+        .collect();
+    // the numbers take as many rows as their wrapped tasks
+    let nums_widget = List::new(nums.iter().zip(&rows).enumerate().map(|(i, (num, row))| {
+        let mut lines = vec![Line::raw(num.as_str())];
+        lines.resize(row.lines as usize, Line::default());
+        ListItem::from(Text::from(lines)).style(marked_style(i))
     }))
-    .block(list_block)
+    .block(list_block.clone())
     .highlight_style(model.config.theme.selected);
+    let list_widget = List::new(items)
+        .block(list_block)
+        .highlight_style(model.config.theme.selected);
+
+    // the widgets borrow the tasks, so the scroll offset is written back after rendering
+    let mut list_state = model.list_state.clone();
+    f.render_stateful_widget(nums_widget, layout[0], &mut list_state);
+    f.render_stateful_widget(list_widget, layout[1], &mut list_state);
+    model.list_state = list_state;
+    model.mouse_areas.list = list_block_inner(layout[1]);
+    model.mouse_areas.list_offset = model.list_state.offset();
+    model.mouse_areas.rows = rows;
+}
+
+/// the rows of the list inside its top and bottom borders
+fn list_block_inner(area: Rect) -> Rect {
+    Block::new()
+        .borders(Borders::BOTTOM | Borders::TOP)
+        .inner(area)
+}
+
+/// the columns of the checkbox and the tags of a task drawn from the column
+fn row_area(x: u16, task: &Task) -> RowArea {
+    let mut column = x;
+    let mut area = RowArea::default();
+    for (i, section) in task.arr.iter().enumerate() {
+        let width = Span::raw(section.1.as_str()).width() as u16;
+        if i == 0 {
+            area.checkbox = column..column + width;
+        } else if matches!(section.0, TaskStringTag::Project | TaskStringTag::Context) {
+            // the leading space isn't part of the tag
+            area.tags
+                .push((column + 1..column + width, section.1.trim().to_string()));
+        }
+        column += width;
+    }
+    area
+}
+
+fn render_detail(model: &Model, area: Rect, f: &mut Frame<'_>) {
+    let block = Block::bordered()
+        .title_top("Details")
+        .padding(Padding::horizontal(1));
+    let Some(task) = model.selected_task() else {
+        f.render_widget(Paragraph::new("No task is selected").block(block), area);
+        return;
+    };
+    let theme = &model.config.theme;
+    let field = |name: &str, value: String, color: Color| {
+        Line::from(vec![
+            Span::styled(format!("{name:<11}"), Style::new().bold()),
+            Span::styled(value, Style::new().fg(color)),
+        ])
+    };
+
+    let status = if task.done {
+        "done"
+    } else if model.blocked.contains(&task.uid) {
+        "blocked"
+    } else {
+        "open"
+    };
+    let mut lines = vec![
+        field("Status", status.to_string(), theme.text),
+        field(
+            "Priority",
+            task.priority()
+                .map_or("none".to_string(), |p| p.to_string()),
+            theme.priority,
+        ),
+    ];
+    let (created, completed) = task.dates();
+    if let Some(created) = created {
+        lines.push(field("Created", created.to_string(), theme.text));
+    }
+    if let Some(completed) = completed {
+        lines.push(field("Completed", completed.to_string(), theme.text));
+    }
+    if let Some(created) = created {
+        let end = completed.unwrap_or_else(|| Local::now().date_naive());
+        let days = (end - created).num_days();
+        lines.push(field("Age", format!("{days} days"), theme.text));
+    }
+    let projects: Vec<String> = task.projects().map(|p| format!("+{p}")).collect();
+    if !projects.is_empty() {
+        lines.push(field("Projects", projects.join(" "), theme.project));
+    }
+    let contexts: Vec<String> = task.contexts().map(|c| format!("@{c}")).collect();
+    if !contexts.is_empty() {
+        lines.push(field("Contexts", contexts.join(" "), theme.context));
+    }
+    for (key, value) in task.key_values() {
+        lines.push(field(key, value.to_string(), theme.kv));
+    }
+    if let Some(due) = task.next_due() {
+        lines.push(field("Next due", due, theme.kv));
+    }
+    // the note key is listed with the other keys, the preview follows them
+    if let Some(ref preview) = model.note_preview {
+        lines.push(Line::raw(""));
+        match preview.text {
+            Some(ref note) => lines.extend(
+                note.lines()
+                    .take(NOTE_PREVIEW_LINES)
+                    .map(|l| Line::styled(l.to_string(), Style::new().fg(theme.completed_text))),
+            ),
+            None => lines.push(Line::styled(
+                "The note file can't be read",
+                Style::new().fg(theme.completed_text),
+            )),
+        }
+    }
 
-    f.render_stateful_widget(nums_widget, layout[0], &mut model.list_state);
-    f.render_stateful_widget(list_widget, layout[1], &mut model.list_state);
+    let para = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(block);
+    f.render_widget(para, area);
 }
 
 fn render_autocomplete(
@@ -249,18 +789,109 @@ fn render_autocomplete(
             .style(Style::default().on_white().black())
             .block(block);
 
-            let rect = Rect::new(
-                cursor_x + if is_search { 0 } else { 1 },
-                layout.y + if is_search { 1 } else { 2 },
-                20,
-                auto_complete.list.len() as u16,
-            );
+            // the list is moved left and shortened to stay on the screen
+            let screen = f.area();
+            let width = 20.min(screen.width);
+            let x = (cursor_x + if is_search { 0 } else { 1 }).min(screen.right() - width);
+            let y = (layout.y + if is_search { 1 } else { 2 }).min(screen.bottom());
+            let height = (auto_complete.list.len() as u16).min(screen.bottom() - y);
+            let rect = Rect::new(x, y, width, height);
             f.render_widget(Clear, rect);
             f.render_stateful_widget(list_widget, rect, &mut auto_complete.list_state)
         }
     }
 }
 
+fn render_substitute_preview(
+    preview: &mut SubstitutePreview,
+    theme: &Theme,
+    chunks: &std::rc::Rc<[Rect]>,
+    f: &mut Frame<'_>,
+) {
+    let rect = centered_rect(80, 60, chunks[1]);
+    let count = preview.changes.len();
+    let block = Block::bordered()
+        .title_top(format!(
+            "Substitute in {count} task{} - y: Apply  n: Cancel",
+            if count == 1 { "" } else { "s" }
+        ))
+        .title_alignment(ratatui::layout::Alignment::Center);
+    let list = preview
+        .changes
+        .iter()
+        .map(|change| {
+            ListItem::from(Text::from(vec![
+                Line::styled(format!("- {}", change.old), theme.completed_text),
+                Line::styled(format!("+ {}", change.new), theme.text),
+            ]))
+        })
+        .collect::<List>()
+        .block(block)
+        .highlight_style(Style::new().bold());
+    f.render_widget(Clear, rect);
+    f.render_stateful_widget(list, rect, &mut preview.list_state)
+}
+
+fn render_tag_manager(
+    manager: &mut TagManager,
+    theme: &Theme,
+    chunks: &std::rc::Rc<[Rect]>,
+    f: &mut Frame<'_>,
+) {
+    let rect = centered_rect(50, 60, chunks[1]);
+    let title = match manager.merge_source {
+        Some(ref source) => format!("Merge {source} into - m/Enter: Merge  Esc: Cancel"),
+        None => "Projects and Contexts - r: Rename  m: Merge  x: Delete".to_string(),
+    };
+    let block = Block::bordered()
+        .title_top(title)
+        .title_alignment(ratatui::layout::Alignment::Center);
+    let width = block.inner(rect).width as usize;
+    let list = manager
+        .entries
+        .iter()
+        .map(|entry| {
+            let color = if entry.tag.starts_with('+') {
+                theme.project
+            } else {
+                theme.context
+            };
+            let count = (entry.open + entry.done).to_string();
+            let gap =
+                width.saturating_sub(entry.depth * 2 + entry.tag.chars().count() + count.len() + 1);
+            let marker = if manager.merge_source.as_ref() == Some(&entry.tag) {
+                "*"
+            } else {
+                " "
+            };
+            ListItem::from(Line::from(vec![
+                Span::raw("  ".repeat(entry.depth)),
+                Span::styled(entry.tag.as_str(), color),
+                Span::raw(" ".repeat(gap)),
+                Span::styled(count, theme.text),
+                Span::raw(marker),
+            ]))
+        })
+        .collect::<List>()
+        .block(block)
+        .highlight_style(theme.selected);
+    f.render_widget(Clear, rect);
+    f.render_stateful_widget(list, rect, &mut manager.list_state);
+
+    if let Some(ref input) = manager.rename {
+        let input_rect = Rect::new(rect.x, rect.bottom().saturating_sub(3), rect.width, 3);
+        let width = input_rect.width.max(3) - 3;
+        let scroll = input.visual_scroll(width as usize);
+        let input_widget = Paragraph::new(input.value())
+            .scroll((0, scroll as u16))
+            .block(Block::bordered().title_top("Rename to"));
+        f.render_widget(Clear, input_rect);
+        f.render_widget(input_widget, input_rect);
+        let cursor_x = input_rect.x + (input.visual_cursor().max(scroll) - scroll) as u16 + 1;
+        f.set_cursor_position(Position::new(cursor_x, input_rect.y + 1));
+    }
+}
+
 fn render_saved_searches_list(model: &mut Model, chunks: &std::rc::Rc<[Rect]>, f: &mut Frame<'_>) {
     if let AppState::SavedSearches = model.app_state {
         let rect = centered_rect(50, 50, chunks[1]);
@@ -274,25 +905,26 @@ fn render_saved_searches_list(model: &mut Model, chunks: &std::rc::Rc<[Rect]>, f
             .block(list_block)
             .highlight_style(model.config.theme.selected);
         f.render_widget(Clear, rect);
-        f.render_stateful_widget(list, rect, &mut model.saved_searches.list_state)
+        f.render_stateful_widget(list, rect, &mut model.saved_searches.list_state);
+        model.mouse_areas.saved_searches = Block::bordered().inner(rect);
+        model.mouse_areas.saved_searches_offset = model.saved_searches.list_state.offset();
+    } else {
+        model.mouse_areas.saved_searches = Rect::default();
     }
 }
 
+/// a popup in the middle of the area taking a percentage of it, but at least the minimum size
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
+    let size = |length: u16, percent: u16, min: u16| {
+        let scaled = (length as u32 * percent as u32 / 100) as u16;
+        scaled.max(min).min(length)
+    };
+    let width = size(r.width, percent_x, POPUP_MIN_WIDTH);
+    let height = size(r.height, percent_y, POPUP_MIN_HEIGHT);
+    Rect::new(
+        r.x + (r.width - width) / 2,
+        r.y + (r.height - height) / 2,
+        width,
+        height,
+    )
 }
@@ -0,0 +1,333 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui_input::Input;
+
+use crate::readline;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Insert,
+    Normal,
+}
+
+/// Vim style modal editing of an input, enabled with `vim_mode` in the config
+#[derive(Debug, Default)]
+pub struct Vim {
+    pub mode: Mode,
+    /// keys typed in normal mode which don't make a command yet, like `c` or `ci`
+    pending: String,
+    /// the keys of the last change and the text it inserted, repeated with `.`
+    last_change: Option<(String, String)>,
+    /// the keys of the change which started insert mode, finished when leaving it
+    recording: Option<String>,
+    inserted: String,
+}
+
+/// what a command typed in normal mode does
+enum Command {
+    Incomplete,
+    Unknown,
+    Motion,
+    Change { insert: bool },
+}
+
+impl Vim {
+    /// starts editing a new text in insert mode
+    pub fn reset(&mut self) {
+        self.mode = Mode::Insert;
+        self.pending.clear();
+        self.recording = None;
+        self.inserted.clear();
+    }
+
+    pub fn pending(&self) -> &str {
+        &self.pending
+    }
+
+    /// Handles the key, returns false for the keys the input leaves to the app like `Enter`,
+    /// or `Esc` in normal mode
+    pub fn handle_key(
+        &mut self,
+        input: &mut Input,
+        key: KeyEvent,
+        kill_buffer: &mut String,
+    ) -> bool {
+        if matches!(
+            key.code,
+            KeyCode::Enter | KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down
+        ) || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+        {
+            return false;
+        }
+        match self.mode {
+            Mode::Insert => {
+                if key.code == KeyCode::Esc {
+                    self.enter_normal(input);
+                    if let Some(keys) = self.recording.take() {
+                        self.last_change = Some((keys, std::mem::take(&mut self.inserted)));
+                    }
+                    return true;
+                }
+                let plain = !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+                match key.code {
+                    KeyCode::Char(c) if plain => self.inserted.push(c),
+                    KeyCode::Backspace => {
+                        self.inserted.pop();
+                    }
+                    _ => {}
+                }
+                readline::handle_key(input, key, kill_buffer);
+                true
+            }
+            Mode::Normal => {
+                let KeyCode::Char(c) = key.code else {
+                    if key.code == KeyCode::Esc && self.pending.is_empty() {
+                        return false;
+                    }
+                    self.pending.clear();
+                    return true;
+                };
+                if c == '.' && self.pending.is_empty() {
+                    self.repeat(input, kill_buffer);
+                    return true;
+                }
+                self.pending.push(c);
+                let keys = self.pending.clone();
+                match self.command(input, &keys, kill_buffer) {
+                    Command::Incomplete => return true,
+                    Command::Unknown | Command::Motion => {}
+                    Command::Change { insert: false } => {
+                        self.last_change = Some((keys, String::new()));
+                    }
+                    Command::Change { insert: true } => {
+                        self.mode = Mode::Insert;
+                        self.recording = Some(keys);
+                        self.inserted.clear();
+                    }
+                }
+                self.pending.clear();
+                true
+            }
+        }
+    }
+
+    /// leaves insert mode, the cursor moves back onto the last char like in vim
+    fn enter_normal(&mut self, input: &mut Input) {
+        self.mode = Mode::Normal;
+        let cursor = input.cursor().saturating_sub(1);
+        set(input, chars(input), cursor);
+    }
+
+    fn repeat(&mut self, input: &mut Input, kill_buffer: &mut String) {
+        let Some((keys, inserted)) = self.last_change.clone() else {
+            return;
+        };
+        if let Command::Change { insert: true } = self.command(input, &keys, kill_buffer) {
+            let mut text = chars(input);
+            let cursor = input.cursor();
+            text.splice(cursor..cursor, inserted.chars());
+            set(input, text, cursor + inserted.chars().count());
+            self.enter_normal(input);
+        }
+    }
+
+    fn command(&mut self, input: &mut Input, keys: &str, kill_buffer: &mut String) -> Command {
+        let text = chars(input);
+        let len = text.len();
+        let cursor = input.cursor().min(len);
+        let motion = |input: &mut Input, to: usize| {
+            set(input, text.clone(), to.min(len.saturating_sub(1)));
+            Command::Motion
+        };
+        match keys {
+            "d" | "c" | "ci" | "di" => Command::Incomplete,
+            "h" => motion(input, cursor.saturating_sub(1)),
+            "l" => motion(input, cursor + 1),
+            "0" => motion(input, 0),
+            "$" => motion(input, len),
+            "w" => motion(input, next_word_start(&text, cursor)),
+            "b" => motion(input, prev_word_start(&text, cursor)),
+            "e" => motion(input, word_end(&text, cursor)),
+            "i" => Command::Change { insert: true },
+            "a" => {
+                set(input, text, (cursor + 1).min(len));
+                Command::Change { insert: true }
+            }
+            "I" => {
+                set(input, text, 0);
+                Command::Change { insert: true }
+            }
+            "A" => {
+                set(input, text, len);
+                Command::Change { insert: true }
+            }
+            "x" if cursor < len => delete(input, text, cursor..cursor + 1, kill_buffer, false),
+            "D" => delete(input, text, cursor..len, kill_buffer, false),
+            "dd" => delete(input, text, 0..len, kill_buffer, false),
+            "cc" | "S" => delete(input, text, 0..len, kill_buffer, true),
+            "dw" => {
+                let end = next_word_start(&text, cursor).max(cursor);
+                let end = if end == cursor { len } else { end };
+                delete(input, text, cursor..end, kill_buffer, false)
+            }
+            // like in vim `cw` changes to the end of the word and leaves the space after it
+            "cw" => {
+                let end = match text.get(cursor) {
+                    Some(&c) if class(c) != 0 => inner_word(&text, cursor).end,
+                    _ => (word_end(&text, cursor) + 1).min(len),
+                };
+                delete(input, text, cursor..end, kill_buffer, true)
+            }
+            "diw" | "ciw" => {
+                let range = inner_word(&text, cursor);
+                delete(input, text, range, kill_buffer, keys == "ciw")
+            }
+            _ => Command::Unknown,
+        }
+    }
+}
+
+fn chars(input: &Input) -> Vec<char> {
+    input.value().chars().collect()
+}
+
+fn set(input: &mut Input, text: Vec<char>, cursor: usize) {
+    let cursor = cursor.min(text.len());
+    *input = Input::new(text.into_iter().collect()).with_cursor(cursor);
+}
+
+fn delete(
+    input: &mut Input,
+    mut text: Vec<char>,
+    range: std::ops::Range<usize>,
+    kill_buffer: &mut String,
+    insert: bool,
+) -> Command {
+    let start = range.start;
+    let killed: String = text.drain(range).collect();
+    if !killed.is_empty() {
+        *kill_buffer = killed;
+    }
+    // in normal mode the cursor stays on a char
+    let cursor = if insert || start < text.len() {
+        start
+    } else {
+        text.len().saturating_sub(1)
+    };
+    set(input, text, cursor);
+    Command::Change { insert }
+}
+
+/// whitespace, word chars and punctuation are the three kinds of chars words are made of
+fn class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+fn next_word_start(text: &[char], cursor: usize) -> usize {
+    let Some(&c) = text.get(cursor) else {
+        return cursor;
+    };
+    let mut i = cursor;
+    while i < text.len() && class(text[i]) == class(c) && class(c) != 0 {
+        i += 1;
+    }
+    while i < text.len() && class(text[i]) == 0 {
+        i += 1;
+    }
+    i
+}
+
+fn prev_word_start(text: &[char], cursor: usize) -> usize {
+    let mut i = cursor.min(text.len());
+    while i > 0 && class(text[i - 1]) == 0 {
+        i -= 1;
+    }
+    if i == 0 {
+        return 0;
+    }
+    let kind = class(text[i - 1]);
+    while i > 0 && class(text[i - 1]) == kind {
+        i -= 1;
+    }
+    i
+}
+
+fn word_end(text: &[char], cursor: usize) -> usize {
+    let mut i = cursor + 1;
+    while i < text.len() && class(text[i]) == 0 {
+        i += 1;
+    }
+    if i >= text.len() {
+        return text.len().saturating_sub(1);
+    }
+    let kind = class(text[i]);
+    while i + 1 < text.len() && class(text[i + 1]) == kind {
+        i += 1;
+    }
+    i
+}
+
+/// the chars of the same kind around the cursor
+fn inner_word(text: &[char], cursor: usize) -> std::ops::Range<usize> {
+    let Some(&c) = text.get(cursor) else {
+        return cursor..cursor;
+    };
+    let kind = class(c);
+    let mut start = cursor;
+    while start > 0 && class(text[start - 1]) == kind {
+        start -= 1;
+    }
+    let mut end = cursor;
+    while end < text.len() && class(text[end]) == kind {
+        end += 1;
+    }
+    start..end
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+    use tui_input::Input;
+
+    use super::{Mode, Vim};
+
+    fn keys(vim: &mut Vim, input: &mut Input, keys: &str) {
+        let mut kill_buffer = String::new();
+        for c in keys.chars() {
+            let code = match c {
+                '⎋' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+            vim.handle_key(input, KeyEvent::from(code), &mut kill_buffer);
+        }
+    }
+
+    #[test]
+    fn vim_motions_and_changes() {
+        let mut vim = Vim::default();
+        let mut input = Input::new("call bob about +budget".to_string());
+        keys(&mut vim, &mut input, "⎋0");
+        assert_eq!(vim.mode, Mode::Normal);
+        keys(&mut vim, &mut input, "w");
+        assert_eq!(input.cursor(), 5);
+        keys(&mut vim, &mut input, "e");
+        assert_eq!(input.cursor(), 7);
+        keys(&mut vim, &mut input, "ciwjoe⎋");
+        assert_eq!(input.value(), "call joe about +budget");
+        assert_eq!(vim.mode, Mode::Normal);
+        keys(&mut vim, &mut input, "w.");
+        assert_eq!(input.value(), "call joe joe +budget");
+        keys(&mut vim, &mut input, "bdw");
+        assert_eq!(input.value(), "call joe +budget");
+        keys(&mut vim, &mut input, "dd");
+        assert_eq!(input.value(), "");
+    }
+}